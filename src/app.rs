@@ -18,6 +18,15 @@ pub fn list_available_modes() {
         println!("  ✅ {}: {}", decoder.as_str(), decoder.description());
     }
 
+    let cameras = crate::frame_extraction::list_camera_devices();
+    if !cameras.is_empty() {
+        println!("\n📷 检测到的摄像头设备:");
+        for device in cameras {
+            println!("  ✅ {}", device);
+        }
+        println!("  💡 用 --input <设备路径或索引> 把它们当视频源直接喂进转换流水线");
+    }
+
     println!("\n💡 使用方法:");
     println!("  cargo run -- --converter ffmpeg --decoder ffmpeg    # 测试FFmpeg转换器 + FFmpeg解码器");
     println!("  cargo run -- --converter wgpu --decoder ffmpeg      # 测试WGPU转换器 + FFmpeg解码器");
@@ -38,12 +47,18 @@ pub async fn show_help_and_demo(cli: &Cli) -> Result<()> {
     println!("  3. 测试不同组合:");
     println!("     cargo run -- --converter wgpu --decoder opencv --frames 10");
 
-    if Path::new(&cli.input).exists() {
+    if crate::frame_extraction::is_camera_source(&cli.input) {
+        println!("\n📷 检测到摄像头输入: {}", cli.input);
+        println!("💡 可以运行: cargo run -- --converter ffmpeg --decoder ffmpeg --input /dev/video0");
+    } else if crate::frame_extraction::is_network_source(&cli.input) {
+        println!("\n📡 检测到网络直播流输入: {}", cli.input);
+        println!("💡 直播流没有总时长，记得用 --frames 或 --sample-fps 控制提取节奏");
+    } else if Path::new(&cli.input).exists() {
         println!("\n📁 检测到输入文件: {}", cli.input);
         println!("💡 可以运行: cargo run -- --converter ffmpeg --decoder ffmpeg --frames 3");
     } else {
         println!("\n⚠️  输入文件 {} 不存在", cli.input);
-        println!("💡 请将测试视频文件命名为 input.mp4 或使用 --input 指定文件路径");
+        println!("💡 请将测试视频文件命名为 input.mp4 或使用 --input 指定文件路径，或传入摄像头设备（如 /dev/video0）");
     }
 
     Ok(())
@@ -1,15 +1,305 @@
 use anyhow::Result;
 use std::path::Path;
-use crate::converters::ConversionMode;
+use std::time::Instant;
+use serde::Serialize;
+use crate::converters::{ConversionMode, ConverterFactory, FrameData};
 use crate::cli::Cli;
 
-pub async fn run_benchmark(_cli: &Cli) -> Result<()> {
-    println!("基准测试功能已移除，请使用单模式测试");
+/// 单个转换模式的性能统计，可序列化成JSON/CSV用于跨commit的回归追踪。
+#[derive(Debug, Default, Serialize)]
+pub struct ConversionStats {
+    pub frames_processed: u32,
+    pub total_time_ms: u64,
+    pub avg_time_per_frame_ms: f64,
+    pub fps: f64,
+    pub min_time_ms: u64,
+    pub max_time_ms: u64,
+    /// 转换时实际使用的worker线程数，好让基准测试表格反映出并行带来的加速比。
+    pub worker_threads: usize,
+}
+
+impl ConversionStats {
+    pub fn new() -> Self {
+        Self {
+            min_time_ms: u64::MAX,
+            ..Default::default()
+        }
+    }
+
+    pub fn record_frame(&mut self, duration_ms: u64) {
+        self.frames_processed += 1;
+        self.total_time_ms += duration_ms;
+        self.min_time_ms = self.min_time_ms.min(duration_ms);
+        self.max_time_ms = self.max_time_ms.max(duration_ms);
+        self.avg_time_per_frame_ms = self.total_time_ms as f64 / self.frames_processed as f64;
+        if self.avg_time_per_frame_ms > 0.0 {
+            self.fps = 1000.0 / self.avg_time_per_frame_ms;
+        }
+    }
+
+    pub fn print_summary(&self, mode: ConversionMode) {
+        println!("\n📊 {} 转换性能统计:", mode.description());
+        println!("  🎞️  处理帧数: {}", self.frames_processed);
+        println!("  ⏱️  总耗时: {:.2}秒", self.total_time_ms as f64 / 1000.0);
+        println!("  📈 平均每帧: {:.2}ms", self.avg_time_per_frame_ms);
+        println!("  ⚡ 最快耗时: {}ms", self.min_time_ms);
+        println!("  🐌 最慢耗时: {}ms", self.max_time_ms);
+        println!("  🚀 转换FPS: {:.1}", self.fps);
+        println!("  🧵 worker线程数: {}", self.worker_threads);
+    }
+}
+
+/// 单个模式在一次基准测试里的完整记录。
+#[derive(Debug, Serialize)]
+pub struct ModeReport {
+    pub mode: String,
+    pub description: String,
+    pub stats: ConversionStats,
+}
+
+/// 一次`run_benchmark`的完整结果：输入信息 + 各模式的`ModeReport`。
+/// 落成JSON/CSV之后就能跟之前的commit对比，不用再盯着终端表格肉眼比对。
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub input: String,
+    pub width: u32,
+    pub height: u32,
+    pub frame_count: u32,
+    pub modes: Vec<ModeReport>,
+}
+
+impl BenchmarkReport {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_csv(&self) -> Result<String> {
+        let mut csv = String::from("mode,description,frames_processed,total_time_ms,avg_time_per_frame_ms,fps,min_time_ms,max_time_ms,worker_threads\n");
+        for report in &self.modes {
+            let stats = &report.stats;
+            csv.push_str(&format!(
+                "{},{},{},{},{:.3},{:.3},{},{},{}\n",
+                report.mode,
+                report.description,
+                stats.frames_processed,
+                stats.total_time_ms,
+                stats.avg_time_per_frame_ms,
+                stats.fps,
+                stats.min_time_ms,
+                stats.max_time_ms,
+                stats.worker_threads,
+            ));
+        }
+        Ok(csv)
+    }
+
+    /// 把报告写成JSON和CSV两份文件，`base_path`不带扩展名，比如`out/benchmark`会写出
+    /// `out/benchmark.json`和`out/benchmark.csv`。
+    pub fn write_to(&self, base_path: &Path) -> Result<()> {
+        std::fs::write(base_path.with_extension("json"), self.to_json()?)?;
+        std::fs::write(base_path.with_extension("csv"), self.to_csv()?)?;
+        Ok(())
+    }
+
+    pub fn print_comparison(&self) {
+        if self.modes.len() < 2 {
+            return;
+        }
+
+        println!("\n🏆 性能对比总结:");
+        println!("┌─────────────────┬──────────┬──────────┬──────────┬──────────┬──────────┬────────┐");
+        println!("│      模式       │ 帧数     │ 平均耗时 │   FPS    │ 最快耗时 │ 最慢耗时 │ 线程数 │");
+        println!("├─────────────────┼──────────┼──────────┼──────────┼──────────┼──────────┼────────┤");
+
+        for report in &self.modes {
+            let stats = &report.stats;
+            println!(
+                "│ {:15} │ {:8} │ {:6.2}ms │ {:6.1}   │ {:6}ms │ {:6}ms │ {:6} │",
+                report.mode,
+                stats.frames_processed,
+                stats.avg_time_per_frame_ms,
+                stats.fps,
+                stats.min_time_ms,
+                stats.max_time_ms,
+                stats.worker_threads
+            );
+        }
+
+        println!("└─────────────────┴──────────┴──────────┴──────────┴──────────┴──────────┴────────┘");
+
+        if let Some(fastest) = self.modes.iter()
+            .max_by(|a, b| a.stats.fps.partial_cmp(&b.stats.fps).unwrap_or(std::cmp::Ordering::Equal)) {
+            println!("🥇 最快模式: {} ({:.1} FPS)", fastest.mode, fastest.stats.fps);
+        }
+    }
+}
+
+/// 对单个转换模式跑一遍已提取好的帧，逐帧计时，产出一份`ModeReport`。
+async fn run_conversion_test(mode: ConversionMode, frames: &[FrameData], worker_threads: usize, verbose: bool) -> Result<ModeReport> {
+    println!("🚀 开始测试 {} 模式...", mode.description());
+
+    let mut converter = ConverterFactory::create_converter(mode, verbose).await?;
+    converter.set_worker_threads(worker_threads);
+    let mut stats = ConversionStats::new();
+    stats.worker_threads = worker_threads;
+
+    for frame in frames {
+        let start = Instant::now();
+
+        match converter.convert(frame).await {
+            Ok(rgb_data) => {
+                let duration_us = start.elapsed().as_micros() as u64;
+                let duration_ms = std::cmp::max(1, duration_us / 1000);
+                stats.record_frame(duration_ms);
+
+                if stats.frames_processed <= 3 {
+                    println!(
+                        "  ✅ 帧#{}: {}x{} -> RGB ({} bytes) 耗时: {}μs",
+                        frame.frame_number, frame.width, frame.height, rgb_data.len(), duration_us
+                    );
+                }
+            }
+            Err(e) => {
+                println!("  ❌ 帧#{} 转换失败: {}", frame.frame_number, e);
+            }
+        }
+    }
+
+    converter.cleanup().await?;
+    stats.print_summary(mode);
+
+    Ok(ModeReport {
+        mode: mode.as_str().to_string(),
+        description: mode.description().to_string(),
+        stats,
+    })
+}
+
+/// WGPU模式专用的基准测试路径：跟`process_frame_with_mode`里WGPU的特殊处理一样，直接
+/// 按批调用`GpuImageProcessor::convert_batch_to_rgb`，不经过`WgpuBatchConverter`的单帧
+/// `convert()`。如果走单帧`convert()`，每次调用都只攒到一帧就被`add_frame`/`flush`的
+/// 兜底逻辑强制刷出去，batch_size=8的批处理和N-deep pipelining根本没机会攒够批次，
+/// 测出来的就是退化成batch=1的GPU吞吐，跟这个模式实际运行时的性能对不上。
+async fn run_wgpu_conversion_test(frames: &[FrameData], verbose: bool) -> Result<ModeReport> {
+    use crate::converters::gpu_backend::BatchFrameData;
+    use crate::converters::wgpu_converter::GpuImageProcessor;
+
+    let mode = ConversionMode::WGPU;
+    println!("🚀 开始测试 {} 模式...", mode.description());
+
+    const BATCH_SIZE: usize = 8; // 跟`WgpuBatchPool::new`/`WgpuBatchConverter::new`的默认batch_size保持一致
+
+    let mut processor = GpuImageProcessor::new(verbose).await?;
+    let mut stats = ConversionStats::new();
+
+    for chunk in frames.chunks(BATCH_SIZE) {
+        let batch_data: Vec<BatchFrameData> = chunk.iter().map(|frame| BatchFrameData {
+            width: frame.width,
+            height: frame.height,
+            data: frame.data.clone(),
+            format: frame.format,
+            color_space: frame.color_space,
+        }).collect();
+
+        let start = Instant::now();
+        let results = processor.convert_batch_to_rgb(&batch_data).await?;
+        let duration_us = start.elapsed().as_micros() as u64;
+        // 把整批的耗时摊平到批内每一帧，这样每帧的计时反映的是批处理+pipeline之后的
+        // 真实单帧成本，而不是把整批的耗时全记在某一帧头上
+        let per_frame_us = std::cmp::max(1, duration_us / chunk.len() as u64);
+        let per_frame_ms = std::cmp::max(1, per_frame_us / 1000);
+
+        for (frame, rgb_data) in chunk.iter().zip(results.iter()) {
+            stats.record_frame(per_frame_ms);
+
+            if stats.frames_processed <= 3 {
+                println!(
+                    "  ✅ 帧#{}: {}x{} -> RGB ({} bytes) 耗时: {}μs",
+                    frame.frame_number, frame.width, frame.height, rgb_data.len(), per_frame_us
+                );
+            }
+        }
+    }
+
+    stats.print_summary(mode);
+
+    Ok(ModeReport {
+        mode: mode.as_str().to_string(),
+        description: mode.description().to_string(),
+        stats,
+    })
+}
+
+/// 跨所有可用转换模式的性能基准测试：先把输入流提取成一份帧集合，然后让每个模式
+/// 各跑一遍同样的帧，这样对比的是转换器本身的速度，不受重复解码的干扰。
+pub async fn run_benchmark(cli: &Cli) -> Result<()> {
+    if !crate::frame_extraction::is_camera_source(&cli.input) && !Path::new(&cli.input).exists() {
+        anyhow::bail!("输入文件不存在: {}", cli.input);
+    }
+
+    let (sender, mut receiver) = tokio::sync::mpsc::channel::<crate::converters::ChannelFrameData>(100);
+
+    let input_path = cli.input.clone();
+    let frames = cli.frames;
+    let fps = cli.fps;
+    let sampling = cli.sampling();
+    let hwaccel = cli.hwaccel;
+    let filter = cli.filter.clone();
+    let extract_task = tokio::task::spawn_local(async move {
+        crate::frame_extraction::extract_frames_streaming(&input_path, frames, fps, sampling, hwaccel, filter.as_deref(), sender).await
+    });
+
+    let mut collected = Vec::new();
+    while let Some(channel_frame) = receiver.recv().await {
+        collected.push(FrameData::from(channel_frame));
+    }
+
+    extract_task.await.map_err(|e| anyhow::anyhow!("Extract task failed: {}", e))??;
+
+    if collected.is_empty() {
+        anyhow::bail!("没有提取到任何帧，无法进行基准测试");
+    }
+    let (width, height) = (collected[0].width, collected[0].height);
+    let frame_count = collected.len() as u32;
+
+    let mut modes = Vec::new();
+    for mode in ConverterFactory::available_modes() {
+        // WGPU有自己的批处理专用测试路径（见`run_wgpu_conversion_test`），其余模式走
+        // 逐帧测试；某个模式（典型如没有可用GPU adapter的机器上的WGPU）失败不该拖垮
+        // 整次基准测试——跳过它，把其余模式的结果照样写进报告。
+        let result = if mode == ConversionMode::WGPU {
+            run_wgpu_conversion_test(&collected, cli.verbose).await
+        } else {
+            run_conversion_test(mode, &collected, cli.worker_threads, cli.verbose).await
+        };
+
+        match result {
+            Ok(report) => modes.push(report),
+            Err(e) => println!("⚠️ {} 模式测试失败，已跳过: {}", mode.description(), e),
+        }
+    }
+
+    if modes.is_empty() {
+        anyhow::bail!("所有转换模式都测试失败，无法生成基准报告");
+    }
+
+    let report = BenchmarkReport {
+        input: cli.input.clone(),
+        width,
+        height,
+        frame_count,
+        modes,
+    };
+
+    report.print_comparison();
+    report.write_to(Path::new(&cli.benchmark_output))?;
+    println!("\n💾 已写出结构化报告: {0}.json / {0}.csv", cli.benchmark_output);
+
     Ok(())
 }
 
 pub async fn run_single_mode(mode: ConversionMode, cli: &Cli) -> Result<()> {
-    if !Path::new(&cli.input).exists() {
+    if !crate::frame_extraction::is_camera_source(&cli.input) && !Path::new(&cli.input).exists() {
         anyhow::bail!("输入文件不存在: {}", cli.input);
     }
 
@@ -21,21 +311,32 @@ pub async fn run_single_mode(mode: ConversionMode, cli: &Cli) -> Result<()> {
     
     let (sender, receiver) = tokio::sync::mpsc::channel::<crate::converters::ChannelFrameData>(100);
     
-    let output_dir = if save_images { 
-        Some(output_path) 
-    } else { 
-        None 
+    let output_dir = if save_images {
+        Some(output_path)
+    } else {
+        None
     };
-    
+    let scale_target = cli.scale_target();
+    let output_format = cli.output_format;
+    let grid_layout = cli.grid_layout();
+    let worker_threads = cli.worker_threads;
+    let verbose = cli.verbose;
+    let sampling = cli.sampling();
+    let hwaccel = cli.hwaccel;
+    let filter = cli.filter.clone();
+
     let convert_task = tokio::task::spawn_local(async move {
-        crate::converters::process_frame_with_mode(receiver, mode, output_dir).await
+        crate::converters::process_frame_with_mode(receiver, mode, output_dir, scale_target, output_format, grid_layout, worker_threads, verbose).await
     });
-    
+
     let extract_task = tokio::task::spawn_local(async move {
         crate::frame_extraction::extract_frames_streaming(
             &input_path,
             frames,
             fps,
+            sampling,
+            hwaccel,
+            filter.as_deref(),
             sender,
         ).await
     });
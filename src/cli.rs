@@ -37,9 +37,112 @@ pub struct Cli {
     #[arg(short, long)]
     pub list_modes: bool,
 
+    /// 跨所有可用转换模式运行一次性能基准测试，而不是用--converter指定的单一模式
+    #[arg(short, long)]
+    pub benchmark: bool,
+
+    /// 基准测试报告的输出路径前缀（不带扩展名），会写出对应的.json和.csv两个文件
+    #[arg(long, default_value = "benchmark_report")]
+    pub benchmark_output: String,
+
     /// 显示详细的FFmpeg日志信息
     #[arg(long)]
     pub verbose: bool,
+
+    /// 输出宽度，和--height一起用，用于生成固定尺寸的缩略图（不设置则保持源分辨率）
+    #[arg(long)]
+    pub width: Option<u32>,
+
+    /// 输出高度，和--width一起用
+    #[arg(long)]
+    pub height: Option<u32>,
+
+    /// 缩放重采样算法，只有同时设置了--width和--height才生效
+    #[arg(long, value_enum, default_value = "bilinear")]
+    pub scale_filter: crate::converters::ScaleFilter,
+
+    /// 输出像素格式：rgb/bgr/rgba/bgra，默认保持packed RGB24
+    #[arg(long, value_enum, default_value = "rgb")]
+    pub output_format: crate::converters::OutputFormat,
+
+    /// contact sheet网格的行数，和--grid-cols一起用，把多帧拼成一张mosaic图而不是一帧一个文件
+    #[arg(long)]
+    pub grid_rows: Option<u32>,
+
+    /// contact sheet网格的列数，和--grid-rows一起用
+    #[arg(long)]
+    pub grid_cols: Option<u32>,
+
+    /// contact sheet每格之间的黑边像素宽度，只有同时设置了--grid-rows和--grid-cols才生效
+    #[arg(long, default_value = "0")]
+    pub grid_padding: u32,
+
+    /// CPU转换器（manual/yuvutils/libyuv/opencv软件回退路径）单帧内部按行拆分的worker
+    /// 线程数，类似dav1d的n_threads，默认是可用的CPU核心数
+    #[arg(long, default_value_t = default_worker_threads())]
+    pub worker_threads: usize,
+
+    /// 硬件加速解码类型（cuda/vaapi/videotoolbox/qsv），默认none走纯软件解码；设备创建
+    /// 或格式协商失败会自动回退到软件解码，不会中断提取流程
+    #[arg(long, value_enum, default_value = "none")]
+    pub hwaccel: crate::frame_extraction::HwAccel,
+
+    /// 在解码和发送之间插入一条FFmpeg libavfilter滤镜链（逗号分隔的滤镜描述，比如
+    /// "scale=320:-1,yadif,fps=5"），可以缩略图、去隔行、统一输出帧率；不设置则跳过
+    #[arg(long)]
+    pub filter: Option<String>,
+
+    /// 开启场景变化智能采样：按画面变化量输出帧，而不是固定间隔/帧率，避免静止
+    /// 画面输出大量近似重复帧。--fps/--frames仍然分别决定最小采样间隔和硬上限
+    #[arg(long)]
+    pub scene_detect: bool,
+
+    /// 场景变化判定阈值（0..1）：32x32亮度缩略图相对上一次输出帧的平均绝对差超过
+    /// 这个比例才输出新帧，只有--scene-detect时才生效
+    #[arg(long, default_value = "0.3")]
+    pub scene_threshold: f64,
+}
+
+/// `--worker-threads`的默认值：跟dav1d的n_threads一样，默认吃满所有可用CPU核心。
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+impl Cli {
+    /// 把--width/--height/--scale-filter合并成一个`ScaleTarget`；两个尺寸缺一个就不缩放。
+    pub fn scale_target(&self) -> Option<crate::converters::ScaleTarget> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Some(crate::converters::ScaleTarget {
+                width,
+                height,
+                filter: self.scale_filter,
+            }),
+            _ => None,
+        }
+    }
+
+    /// 把--grid-rows/--grid-cols/--grid-padding合并成一个`GridLayout`；两个维度缺一个
+    /// 就不启用contact sheet，走照常的逐帧输出。
+    pub fn grid_layout(&self) -> Option<crate::converters::GridLayout> {
+        match (self.grid_rows, self.grid_cols) {
+            (Some(rows), Some(cols)) => Some(crate::converters::GridLayout {
+                rows,
+                cols,
+                padding: self.grid_padding,
+            }),
+            _ => None,
+        }
+    }
+
+    /// 把--scene-detect/--scene-threshold合并成一个`Sampling`；不开启就还是原来的
+    /// 固定间隔/帧率采样。
+    pub fn sampling(&self) -> crate::frame_extraction::Sampling {
+        if self.scene_detect {
+            crate::frame_extraction::Sampling::SceneChange { threshold: self.scene_threshold }
+        } else {
+            crate::frame_extraction::Sampling::Interval
+        }
+    }
 }
 
  
\ No newline at end of file
@@ -1,44 +1,121 @@
 use anyhow::Result;
-use crate::converters::{YuvToRgbConverter, FrameData};
+use crate::converters::{ColorMatrix, ColorRange, ColorSpace, OutputFormat, ScaleFilter, ScaleTarget, YuvToRgbConverter, FrameData};
+
+/// (源宽, 源高, 格式, 目标宽, 目标高, flags位, 输出像素格式)，用来判断缓存的scaler是否
+/// 还能复用，跟`sws_getCachedContext`的思路一样：参数不变就不用重新创建scaler。
+type ScalerKey = (u32, u32, ffmpeg_next::util::format::Pixel, u32, u32, u32, ffmpeg_next::util::format::Pixel);
+
+fn scale_flags(filter: ScaleFilter) -> ffmpeg_next::software::scaling::Flags {
+    use ffmpeg_next::software::scaling::Flags;
+    match filter {
+        ScaleFilter::Point => Flags::POINT,
+        ScaleFilter::Bilinear => Flags::BILINEAR,
+        ScaleFilter::Bicubic => Flags::BICUBIC,
+    }
+}
+
+/// SWScale原生支持packed RGB/BGR/RGBA/BGRA输出，直接把`OutputFormat`映射到对应的
+/// 目标像素格式，让scaler一步到位产出调用方要的通道顺序，不需要像manual/opencv那样
+/// 再在CPU上套一遍`pack_channels`。
+fn output_pixel(format: OutputFormat) -> ffmpeg_next::util::format::Pixel {
+    use ffmpeg_next::util::format::Pixel;
+    match format {
+        OutputFormat::Rgb => Pixel::RGB24,
+        OutputFormat::Bgr => Pixel::BGR24,
+        OutputFormat::Rgba => Pixel::RGBA,
+        OutputFormat::Bgra => Pixel::BGRA,
+    }
+}
 
 /// FFmpeg SWScale转换器
-/// 
+///
 /// 使用FFmpeg的SWScale库进行高效的YUV到RGB转换
 /// 这是最成熟和优化的实现，适合生产环境使用
 pub struct FfmpegConverter {
     scaler: Option<ffmpeg_next::software::scaling::context::Context>,
+    scaler_key: Option<ScalerKey>,
+    applied_color_space: Option<ColorSpace>,
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
 }
 
 impl FfmpegConverter {
     pub fn new() -> Self {
         Self {
             scaler: None,
+            scaler_key: None,
+            applied_color_space: None,
+            scale_target: None,
+            output_format: OutputFormat::default(),
         }
     }
 
-    /// 确保scaler已初始化，如果没有则创建新的scaler
+    /// 确保scaler已初始化且参数匹配，不匹配（分辨率变化、缩放目标变化、输出格式变化等）
+    /// 才重建，和`sws_getCachedContext`一样只在真的需要时才付重新分配的成本。
     fn ensure_scaler(&mut self, width: u32, height: u32, format: ffmpeg_next::util::format::Pixel) -> Result<()> {
-        if self.scaler.is_none() {
+        let target = self.scale_target.unwrap_or(ScaleTarget { width, height, filter: ScaleFilter::Bilinear });
+        let flags = scale_flags(target.filter);
+        let dst_format = output_pixel(self.output_format);
+        let key: ScalerKey = (width, height, format, target.width, target.height, flags.bits() as u32, dst_format);
+
+        if self.scaler_key != Some(key) {
             let scaler = ffmpeg_next::software::scaling::context::Context::get(
                 format,
                 width,
                 height,
-                ffmpeg_next::util::format::Pixel::RGB24,
-                width,
-                height,
-                ffmpeg_next::software::scaling::Flags::BILINEAR,
+                dst_format,
+                target.width,
+                target.height,
+                flags,
             )?;
             self.scaler = Some(scaler);
+            self.scaler_key = Some(key);
+            // scaler是新建的，之前设置的颜色空间参数没了，强制下一次重新应用
+            self.applied_color_space = None;
         }
         Ok(())
     }
+
+    /// 把帧携带的`ColorSpace`通过`sws_setColorspaceDetails`喂给scaler，这样HD/UHD内容
+    /// 不会被一律按BT.601的系数去转换。只在颜色空间真的变化时才调用一次。
+    fn apply_color_space(&mut self, color_space: ColorSpace) {
+        if self.applied_color_space == Some(color_space) {
+            return;
+        }
+
+        let Some(ref mut scaler) = self.scaler else { return };
+
+        let sws_matrix = match color_space.matrix {
+            ColorMatrix::Bt601 => ffmpeg_next::ffi::SWS_CS_ITU601,
+            ColorMatrix::Bt709 => ffmpeg_next::ffi::SWS_CS_ITU709,
+            ColorMatrix::Bt2020 => ffmpeg_next::ffi::SWS_CS_BT2020,
+        };
+        let full_range = matches!(color_space.range, ColorRange::Full) as i32;
+
+        unsafe {
+            let coefficients = ffmpeg_next::ffi::sws_getCoefficients(sws_matrix as i32);
+            ffmpeg_next::ffi::sws_setColorspaceDetails(
+                scaler.as_mut_ptr(),
+                coefficients,
+                full_range,
+                coefficients,
+                full_range,
+                0,
+                1 << 16,
+                1 << 16,
+            );
+        }
+
+        self.applied_color_space = Some(color_space);
+    }
 }
 
 #[async_trait::async_trait(?Send)]
 impl YuvToRgbConverter for FfmpegConverter {
     async fn convert(&mut self, frame_data: &FrameData) -> Result<Vec<u8>> {
         self.ensure_scaler(frame_data.width, frame_data.height, frame_data.format)?;
-        
+        self.apply_color_space(frame_data.color_space);
+
         // 创建输入帧
         let mut input_frame = ffmpeg_next::util::frame::video::Video::new(
             frame_data.format,
@@ -46,56 +123,56 @@ impl YuvToRgbConverter for FfmpegConverter {
             frame_data.height
         );
         
-        // 为YUV420P格式正确设置三个平面的数据
-        if frame_data.format == ffmpeg_next::util::format::Pixel::YUV420P {
-            let width = frame_data.width as usize;
-            let height = frame_data.height as usize;
-            let y_size = width * height;
-            let uv_size = y_size / 4;
-            
-            if frame_data.data.len() < y_size + 2 * uv_size {
-                anyhow::bail!("Invalid YUV420P data size: expected {}, got {}", 
-                             y_size + 2 * uv_size, frame_data.data.len());
-            }
-            
-            // 设置Y平面
-            let y_data = &frame_data.data[0..y_size];
+        // 按格式把平面/半平面数据拷进ffmpeg帧对应的plane；平面数跟子采样布局都不一样，
+        // 所以每种格式单独算size/offset，而不是指望同一套420P的偏移量。
+        use ffmpeg_next::util::format::Pixel;
+        let width = frame_data.width as usize;
+        let height = frame_data.height as usize;
+        let y_size = width * height;
+
+        let copy_plane = |input_frame: &mut ffmpeg_next::util::frame::video::Video, plane: usize, data: &[u8]| {
             unsafe {
                 std::ptr::copy_nonoverlapping(
-                    y_data.as_ptr(),
-                    input_frame.data_mut(0).as_mut_ptr(),
-                    y_size.min(input_frame.data(0).len())
+                    data.as_ptr(),
+                    input_frame.data_mut(plane).as_mut_ptr(),
+                    data.len().min(input_frame.data(plane).len())
                 );
             }
-            
-            // 设置U平面
-            let u_data = &frame_data.data[y_size..y_size + uv_size];
-            unsafe {
-                std::ptr::copy_nonoverlapping(
-                    u_data.as_ptr(),
-                    input_frame.data_mut(1).as_mut_ptr(),
-                    uv_size.min(input_frame.data(1).len())
-                );
+        };
+
+        match frame_data.format {
+            Pixel::YUV420P => {
+                let uv_size = y_size / 4;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV420P data size: expected {}, got {}",
+                                 y_size + 2 * uv_size, frame_data.data.len());
+                }
+                copy_plane(&mut input_frame, 0, &frame_data.data[0..y_size]);
+                copy_plane(&mut input_frame, 1, &frame_data.data[y_size..y_size + uv_size]);
+                copy_plane(&mut input_frame, 2, &frame_data.data[y_size + uv_size..y_size + 2 * uv_size]);
             }
-            
-            // 设置V平面
-            let v_data = &frame_data.data[y_size + uv_size..y_size + 2 * uv_size];
-            unsafe {
-                std::ptr::copy_nonoverlapping(
-                    v_data.as_ptr(),
-                    input_frame.data_mut(2).as_mut_ptr(),
-                    uv_size.min(input_frame.data(2).len())
-                );
+            Pixel::YUV422P => {
+                let uv_size = width / 2 * height;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV422P data size: expected {}, got {}",
+                                 y_size + 2 * uv_size, frame_data.data.len());
+                }
+                copy_plane(&mut input_frame, 0, &frame_data.data[0..y_size]);
+                copy_plane(&mut input_frame, 1, &frame_data.data[y_size..y_size + uv_size]);
+                copy_plane(&mut input_frame, 2, &frame_data.data[y_size + uv_size..y_size + 2 * uv_size]);
             }
-        } else {
-            // 对于其他格式，使用原来的简单复制方法
-            let data_len = frame_data.data.len();
-            unsafe {
-                std::ptr::copy_nonoverlapping(
-                    frame_data.data.as_ptr(),
-                    input_frame.data_mut(0).as_mut_ptr(),
-                    data_len.min(input_frame.data(0).len())
-                );
+            Pixel::NV12 | Pixel::NV21 => {
+                let uv_size = width / 2 * height / 2 * 2;
+                if frame_data.data.len() < y_size + uv_size {
+                    anyhow::bail!("Invalid NV12/NV21 data size: expected {}, got {}",
+                                 y_size + uv_size, frame_data.data.len());
+                }
+                copy_plane(&mut input_frame, 0, &frame_data.data[0..y_size]);
+                copy_plane(&mut input_frame, 1, &frame_data.data[y_size..y_size + uv_size]);
+            }
+            _ => {
+                // 未特殊处理的格式：退回到只拷贝plane 0的旧行为
+                copy_plane(&mut input_frame, 0, &frame_data.data);
             }
         }
         
@@ -107,11 +184,22 @@ impl YuvToRgbConverter for FfmpegConverter {
             scaler.run(&input_frame, &mut output_frame)?;
         }
         
-        // 提取RGB数据
-        let rgb_size = (frame_data.width * frame_data.height * 3) as usize;
-        let rgb_data = output_frame.data(0)[0..rgb_size].to_vec();
-        
-        Ok(rgb_data)
+        // 提取像素数据：scaler的输出尺寸是scale_target（没设置就等于源分辨率），
+        // 通道数由`output_format`决定——scaler已经原生产出目标格式，这里只是摘取对应长度
+        let (out_width, out_height) = self.scale_target
+            .map(|t| (t.width, t.height))
+            .unwrap_or((frame_data.width, frame_data.height));
+        let pixel_size = (out_width * out_height) as usize * self.output_format.channels();
+        let pixel_data = output_frame.data(0)[0..pixel_size].to_vec();
+
+        Ok(pixel_data)
+    }
+
+    fn set_scale_target(&mut self, target: Option<crate::converters::ScaleTarget>) {
+        self.scale_target = target;
     }
 
-} 
\ No newline at end of file
+    fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+}
\ No newline at end of file
@@ -0,0 +1,773 @@
+use anyhow::{anyhow, Result};
+use crate::converters::ColorSpace;
+use ffmpeg_next::util::format::Pixel;
+
+/// `GpuImageProcessor`需要的最小GPU操作集合：建后端、提交一个批次（分配/写入buffer、
+/// bind group、dispatch都由实现自己决定怎么做）、在需要的时候把最早提交的批次读回来。
+/// 把这层单独抽出来是为了让`wgpu_converter.rs`里`convert_batch_to_rgb`的批次调度逻辑
+/// 不直接依赖`wgpu::`类型——以后要接别的WebGPU实现（比如走Dawn的原生绑定，或者直接
+/// 手写的Vulkan compute路径），只需要在这里新增一个`GpuBackend`实现，不用碰调度逻辑。
+#[async_trait::async_trait(?Send)]
+pub trait GpuBackend: Sized {
+    /// 建一个新的后端实例；`staging_chunk_size`是CPU->GPU上传用的staging缓冲区大小，
+    /// 含义由具体后端定义（`WgpuBackend`直接把它传给自己的`StagingBelt`）。`verbose`
+    /// 对应`Cli::verbose`：打开时`WgpuBackend`会尝试启用GPU时间戳查询、汇报每批次的
+    /// GPU耗时，并给buffer/bind group打上动态标签；不支持`TIMESTAMP_QUERY`的设备上
+    /// 会静默退化成不带时间戳的普通路径。
+    async fn new(staging_chunk_size: u64, verbose: bool) -> Result<Self>;
+
+    /// 提交一个批次的YUV->RGBA计算，不等GPU跑完就返回。批次内所有帧必须是同一种
+    /// 像素格式和色彩空间（参数打包进一份共享的params uniform），格式支持
+    /// YUV420P/YUV422P/YUV444P（平面）和NV12/NV21（半平面），矩阵/量化范围取自
+    /// `BatchFrameData::color_space`，跟CPU路径的`convert_yuv_to_rgb_software`覆盖
+    /// 同一组格式。如果后端内部的pipeline slot已经全部占满，允许先阻塞腾出一个，
+    /// 腾出来的那个批次的结果作为返回值带回。
+    async fn submit_batch(&mut self, frame_data: &[BatchFrameData]) -> Result<Option<Vec<Vec<u8>>>>;
+
+    /// 等最早提交、还没读回的那个批次完成并解包成RGB。调用方只应该在`has_in_flight`
+    /// 返回`true`的时候调用。
+    async fn drain_oldest(&mut self) -> Result<Vec<Vec<u8>>>;
+
+    /// 是否还有提交了但没读回的批次——`convert_batch_to_rgb`末尾排空pipeline时用。
+    fn has_in_flight(&self) -> bool;
+}
+
+/// 提交给GPU批处理的单帧输入：像素数据、尺寸、格式和色彩空间，字段含义跟CPU路径的
+/// `FrameData`对应字段一致。`WgpuBatchPool`从`FrameData`搬运出这些字段攒成一个批次，
+/// `GpuBackend::submit_batch`要求同一批次内这几项（除`data`外）全部一致。
+#[derive(Clone)]
+pub struct BatchFrameData {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub format: Pixel,
+    pub color_space: ColorSpace,
+}
+
+/// GPU路径目前支持的chroma plane布局，对应`ffmpeg_next::util::format::Pixel`的一个
+/// 子集。只编码"怎么摆放"（跟CPU路径`ChromaLayout`是同一个概念，但这里只需要一个
+/// 传给shader的tag，不需要真的持有平面切片），新增格式只需要加一个分支。
+#[cfg(feature = "wgpu-mode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GpuChromaFormat {
+    /// YUV420P：U/V各自一张图，水平/垂直都半采样
+    Planar420,
+    /// YUV422P：U/V各自一张图，只在水平方向半采样
+    Planar422,
+    /// YUV444P：U/V各自一张图，不做色度子采样
+    Planar444,
+    /// NV12/NV21：U/V交织存在同一张图里，shader按stride 2读取；`swapped`为true时是NV21
+    /// （VU顺序），false是NV12（UV顺序）
+    SemiPlanar { swapped: bool },
+}
+
+#[cfg(feature = "wgpu-mode")]
+impl GpuChromaFormat {
+    fn from_pixel(format: Pixel) -> Result<Self> {
+        match format {
+            Pixel::YUV420P => Ok(Self::Planar420),
+            Pixel::YUV422P => Ok(Self::Planar422),
+            Pixel::YUV444P => Ok(Self::Planar444),
+            Pixel::NV12 => Ok(Self::SemiPlanar { swapped: false }),
+            Pixel::NV21 => Ok(Self::SemiPlanar { swapped: true }),
+            other => Err(anyhow!("GPU conversion does not support {:?} format", other)),
+        }
+    }
+
+    /// 编码进params uniform的chroma layout selector，shader侧按这个值决定怎么从
+    /// U/V buffer取样。
+    fn shader_tag(&self) -> u32 {
+        match self {
+            Self::Planar420 => 0,
+            Self::SemiPlanar { .. } => 1,
+            Self::Planar422 => 2,
+            Self::Planar444 => 3,
+        }
+    }
+
+    fn swapped_tag(&self) -> u32 {
+        matches!(self, Self::SemiPlanar { swapped: true }) as u32
+    }
+
+    /// 单帧的chroma plane尺寸：planar格式下是U/V各自一张图的大小；semi-planar下是
+    /// 交织UV图整体的大小（已经包含两个分量）。
+    fn chroma_plane_size(&self, y_size: usize) -> usize {
+        match self {
+            Self::Planar420 => y_size / 4,
+            Self::SemiPlanar { .. } => y_size / 2,
+            Self::Planar422 => y_size / 2,
+            Self::Planar444 => y_size,
+        }
+    }
+
+    /// 单帧chroma部分总共占多少字节：planar格式是U/V两张图各算一次；semi-planar是
+    /// 交织在一起的单张UV图，`chroma_plane_size`已经是整张图的大小，不用再乘2。
+    fn total_chroma_bytes(&self, y_size: usize) -> usize {
+        match self {
+            Self::SemiPlanar { .. } => self.chroma_plane_size(y_size),
+            _ => self.chroma_plane_size(y_size) * 2,
+        }
+    }
+
+    /// U/V两个staging chunk里各自应该从原始帧数据的哪段区间拷贝：`(u_offset, u_len,
+    /// v_offset, v_len)`。semi-planar下v_len是0——交织的UV数据整个落在u_chunk里，
+    /// v_chunk只是垫0占位，shader不会读它。
+    fn plane_spec(&self, y_size: usize) -> (usize, usize, usize, usize) {
+        let uv_size = self.chroma_plane_size(y_size);
+        match self {
+            Self::SemiPlanar { .. } => (y_size, uv_size, y_size, 0),
+            _ => (y_size, uv_size, y_size + uv_size, uv_size),
+        }
+    }
+}
+
+/// Y/U/V平面上传用的staging belt：一池`MAP_WRITE | COPY_SRC`的buffer，帧数据直接写进
+/// mapped memory（尾部顺手补齐到4字节对齐），再靠`copy_buffer_to_buffer`搬进显存里的
+/// 存储缓冲区，省掉`queue.write_buffer`内部再拷贝一次、以及单独一趟`pad_data`分配。
+/// chunk对应的提交一旦确认完成就会被`recycle`重新map好放回池子，下一批次`acquire`
+/// 拿到手就能直接写，不需要现场分配显存或等一轮`map_async`。
+#[cfg(feature = "wgpu-mode")]
+struct StagingBelt {
+    chunk_size: u64,
+    free_chunks: Vec<wgpu::Buffer>,
+}
+
+#[cfg(feature = "wgpu-mode")]
+impl StagingBelt {
+    fn new(chunk_size: u64) -> Self {
+        Self {
+            chunk_size,
+            free_chunks: Vec::new(),
+        }
+    }
+
+    /// 取一个至少能装下`required_size`字节、已经map好可以直接写的chunk：优先复用池子里
+    /// 空闲的buffer，没有合适的就现场`mapped_at_creation`创建一个新的。
+    fn acquire(&mut self, device: &wgpu::Device, required_size: u64) -> wgpu::Buffer {
+        let size = required_size.max(self.chunk_size);
+        if let Some(pos) = self.free_chunks.iter().position(|chunk| chunk.size() >= size) {
+            self.free_chunks.swap_remove(pos)
+        } else {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Staging Belt Chunk"),
+                size,
+                usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            })
+        }
+    }
+
+    /// 归还一个对应提交已经跑完（调用方已经`poll(Wait)`过）的chunk：重新map好再放回
+    /// 空闲池，下次`acquire`复用时不用再等一轮`map_async`。
+    fn recycle(&mut self, device: &wgpu::Device, buffer: wgpu::Buffer) {
+        buffer.slice(..).map_async(wgpu::MapMode::Write, |_| {});
+        let _ = device.poll(wgpu::MaintainBase::Wait);
+        self.free_chunks.push(buffer);
+    }
+}
+
+/// 把一批帧里同一个plane的数据依次写进已经map好的staging chunk，写完的尾部补0到
+/// `padded_len`——跟原来`pad_data`单独一趟分配+拷贝做的事一样，这里省掉了那次分配。
+#[cfg(feature = "wgpu-mode")]
+fn fill_staging_chunk(chunk: &wgpu::Buffer, frame_data: &[BatchFrameData], plane_offset: usize, plane_len: usize, padded_len: u64) {
+    let mut view = chunk.slice(..padded_len).get_mapped_range_mut();
+    let mut written = 0usize;
+    for frame in frame_data {
+        view[written..written + plane_len].copy_from_slice(&frame.data[plane_offset..plane_offset + plane_len]);
+        written += plane_len;
+    }
+    for byte in &mut view[written..] {
+        *byte = 0; // 补齐到4字节对齐（semi-planar的v_chunk走这条路径：plane_len=0，整段都靠这里垫0）
+    }
+}
+
+/// 同时在飞的批次数：一份在GPU上跑compute，一份读回结果，还留一份给CPU打包下一批，
+/// 三档刚好覆盖"GPU忙/CPU打包/CPU读回"这三个阶段不互相等待。
+const PIPELINE_DEPTH: usize = 3;
+
+/// 一个批次专属的GPU资源集合：Y/U/V/输出/读回缓冲区、params uniform、bind group。
+/// 每个pipeline slot一份，这样slot之间互不干扰，K批的compute可以跟K-1批的读回
+/// 同时在GPU队列里跑而不会撞缓冲区。
+#[cfg(feature = "wgpu-mode")]
+struct PipelineSlot {
+    y_buffer: wgpu::Buffer,
+    u_buffer: wgpu::Buffer,
+    v_buffer: wgpu::Buffer,
+    output_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    capacity: usize,
+    cached_size: (u32, u32),
+    /// 上次(重新)分配时用的chroma格式；切换格式（比如YUV420P->NV12）即使分辨率和
+    /// 批次大小都没变，U/V buffer需要的尺寸也不一样，必须当成重建的触发条件之一。
+    cached_chroma: GpuChromaFormat,
+    /// 本slot专属的时间戳resolve/readback一对buffer，只有`verbose`且设备支持
+    /// `TIMESTAMP_QUERY`时才会创建；解析出来的两个u64 tick分别对应compute pass的
+    /// 起止时间。
+    timestamps: Option<SlotTimestampBuffers>,
+}
+
+/// 一个pipeline slot专属的时间戳查询resolve目标：`resolve_buffer`接`resolve_query_set`
+/// 的输出（GPU-only），`readback_buffer`再从它拷一份出来给CPU`map_async`读，两者分开
+/// 是因为resolve目标不允许带`MAP_READ`用途。
+#[cfg(feature = "wgpu-mode")]
+struct SlotTimestampBuffers {
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+/// 已经提交、还没被读回的一个批次：`receiver`在`read_buffer`的`map_async`完成时收到信号。
+/// 连同当时借走的staging chunk一起存着，读回确认完成后再一并归还belt，保证它们在
+/// 对应的`copy_buffer_to_buffer`真正跑完之前不会被复用。
+#[cfg(feature = "wgpu-mode")]
+struct InFlightBatch {
+    slot: usize,
+    batch_size: usize,
+    width: u32,
+    height: u32,
+    y_chunk: wgpu::Buffer,
+    u_chunk: wgpu::Buffer,
+    v_chunk: wgpu::Buffer,
+    receiver: futures::channel::oneshot::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    /// 提交这个批次时的`batch_counter`值，只用于verbose模式下的GPU耗时打印，跟调度
+    /// 逻辑本身无关。
+    batch_index: u64,
+}
+
+/// `GpuBackend`的默认实现：直接用`wgpu`跑compute shader。`GpuImageProcessor`原来所有
+/// 直接碰`wgpu::`类型的部分都搬到了这里；别的后端只需要实现`GpuBackend`这四个方法。
+#[cfg(feature = "wgpu-mode")]
+pub struct WgpuBackend {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    gpu_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    staging_belt: StagingBelt,
+    // 一直保留至少一个备用encoder：`CommandEncoder::finish()`本身没法复用，
+    // 这里的"池子"复用的是"总有一个现成的在手"这件事，省掉每个batch现场分配的开销。
+    encoder_pool: Vec<wgpu::CommandEncoder>,
+    slots: Vec<Option<PipelineSlot>>,
+    free_slots: Vec<usize>,
+    in_flight: std::collections::VecDeque<InFlightBatch>,
+    /// 对应`Cli::verbose`：打开时按批次汇报GPU耗时，并给(重新)分配的buffer/bind group
+    /// 打上带批次号和分辨率的动态标签，方便外部GPU capture工具（RenderDoc等）辨认。
+    verbose: bool,
+    /// 每个slot一对时间戳查询（起/止），只有`verbose`且设备支持`TIMESTAMP_QUERY`时
+    /// 才会创建；`None`表示这次运行完全不产生时间戳开销。
+    query_set: Option<wgpu::QuerySet>,
+    /// `queue.get_timestamp_period()`的缓存值：一个时间戳tick对应多少纳秒，解析
+    /// 查询结果时要乘上它才是实际耗时。
+    timestamp_period_ns: f32,
+    /// 递增的批次计数器，仅用于verbose模式下的日志和调试标签，不影响调度逻辑。
+    batch_counter: u64,
+}
+
+#[cfg(feature = "wgpu-mode")]
+impl WgpuBackend {
+    /// 从池子里拿一个可以直接用的encoder，没有备用的就现场建一个。
+    fn acquire_encoder(&mut self) -> wgpu::CommandEncoder {
+        self.encoder_pool.pop().unwrap_or_else(|| {
+            self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("GPU Encoder"),
+            })
+        })
+    }
+
+    /// 在刚提交完一个encoder之后立刻调用，不用等那次提交跑完。`CommandEncoder::finish()`会消费掉
+    /// 自己，没法真的复用同一个对象，所以这里提前建好下一个备用塞回池子，让下次
+    /// `acquire_encoder`不用在热路径上现场分配。
+    fn release_encoder(&mut self) {
+        self.encoder_pool.push(self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Encoder"),
+        }));
+    }
+
+    /// 确保某个slot的缓冲区能装下这个尺寸/批次大小/chroma格式，不匹配就重建（连带
+    /// bind group）。`batch_index`只在`verbose`时用来给这批(重新)分配的资源打动态
+    /// 标签，不影响缓存判断本身。
+    fn ensure_slot(&mut self, slot_idx: usize, batch_size: usize, width: u32, height: u32, chroma: GpuChromaFormat, batch_index: u64) -> Result<()> {
+        let need_new = match &self.slots[slot_idx] {
+            Some(slot) => slot.capacity < batch_size || slot.cached_size != (width, height) || slot.cached_chroma != chroma,
+            None => true,
+        };
+        if !need_new {
+            return Ok(());
+        }
+
+        let y_size = (width * height) as usize;
+        let uv_size = chroma.chroma_plane_size(y_size);
+        let batch_y_size = (y_size * batch_size) as u64;
+        let batch_uv_size = (uv_size * batch_size) as u64;
+        let batch_rgba_size = (width * height * 4 * batch_size as u32) as u64;
+
+        // verbose时把批次号和分辨率编进标签，好让RenderDoc之类的GPU capture工具能
+        // 认出是哪一批、哪个尺寸的资源；非verbose时保持原来的静态标签，不用现场
+        // 格式化字符串。
+        let label = |name: &str| -> String {
+            if self.verbose {
+                format!("{} [batch {} {}x{} x{}]", name, batch_index, width, height, batch_size)
+            } else {
+                name.to_string()
+            }
+        };
+
+        let y_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("Y Buffer")),
+            size: pad_size(batch_y_size),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let u_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("U Buffer")),
+            size: pad_size(batch_uv_size),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let v_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("V Buffer")),
+            size: pad_size(batch_uv_size),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("Output Buffer")),
+            size: batch_rgba_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("Read Buffer")),
+            size: batch_rgba_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let params_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label("Parameters Buffer")),
+            size: std::mem::size_of::<[u32; 8]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&label("GPU Bind Group")),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: y_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: u_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: v_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: output_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: params_buffer.as_entire_binding() },
+            ],
+        });
+
+        let timestamps = if self.query_set.is_some() {
+            Some(SlotTimestampBuffers {
+                resolve_buffer: self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&label("Timestamp Resolve Buffer")),
+                    size: TIMESTAMP_QUERY_BYTES,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readback_buffer: self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&label("Timestamp Readback Buffer")),
+                    size: TIMESTAMP_QUERY_BYTES,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+            })
+        } else {
+            None
+        };
+
+        self.slots[slot_idx] = Some(PipelineSlot {
+            y_buffer,
+            u_buffer,
+            v_buffer,
+            output_buffer,
+            read_buffer,
+            params_buffer,
+            bind_group,
+            capacity: batch_size,
+            cached_size: (width, height),
+            cached_chroma: chroma,
+            timestamps,
+        });
+
+        Ok(())
+    }
+}
+
+/// 一对时间戳查询（起始+结束）resolve出来的字节数：两个u64 tick。
+#[cfg(feature = "wgpu-mode")]
+const TIMESTAMP_QUERY_BYTES: u64 = 16;
+
+#[cfg(feature = "wgpu-mode")]
+#[async_trait::async_trait(?Send)]
+impl GpuBackend for WgpuBackend {
+    async fn new(staging_chunk_size: u64, verbose: bool) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
+
+        // 时间戳查询只在verbose时才申请：不是所有适配器都支持`TIMESTAMP_QUERY`，
+        // 静默降级成不带GPU耗时统计的普通路径，不中断整个后端的创建。
+        let supports_timestamps = adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        let want_timestamps = verbose && supports_timestamps;
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                required_features: if want_timestamps {
+                    wgpu::Features::TIMESTAMP_QUERY
+                } else {
+                    wgpu::Features::empty()
+                },
+                ..Default::default()
+            })
+            .await?;
+
+        let query_set = want_timestamps.then(|| {
+            device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("GPU Batch Timestamp Queries"),
+                ty: wgpu::QueryType::Timestamp,
+                count: (PIPELINE_DEPTH * 2) as u32,
+            })
+        });
+        let timestamp_period_ns = queue.get_timestamp_period();
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("GPU YUV to RGB Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/yuv_to_rgb_batch.wgsl").into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("GPU Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("GPU Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let gpu_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("GPU Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        Ok(Self {
+            device,
+            queue,
+            gpu_pipeline,
+            bind_group_layout,
+            staging_belt: StagingBelt::new(staging_chunk_size),
+            encoder_pool: Vec::new(),
+            slots: (0..PIPELINE_DEPTH).map(|_| None).collect(),
+            free_slots: (0..PIPELINE_DEPTH).collect(),
+            in_flight: std::collections::VecDeque::new(),
+            verbose,
+            query_set,
+            timestamp_period_ns,
+            batch_counter: 0,
+        })
+    }
+
+    /// 提交一个子批次到一个空闲的pipeline slot，不等GPU跑完就返回。如果三个slot都在飞，
+    /// 先阻塞着`drain_oldest`腾出最早提交的那个——这是整条pipeline里唯一会真正stall
+    /// CPU的地方，返回值就是被腾出来的那个批次的结果（调用方要把它按提交顺序拼起来）。
+    async fn submit_batch(&mut self, frame_data: &[BatchFrameData]) -> Result<Option<Vec<Vec<u8>>>> {
+        let first = &frame_data[0];
+        let (first_width, first_height) = (first.width, first.height);
+        if !frame_data.iter().all(|f| f.width == first_width && f.height == first_height) {
+            return Err(anyhow!("所有帧必须具有相同尺寸"));
+        }
+        if !frame_data.iter().all(|f| f.format == first.format) {
+            return Err(anyhow!("所有帧必须具有相同的像素格式"));
+        }
+        if !frame_data.iter().all(|f| f.color_space == first.color_space) {
+            return Err(anyhow!("所有帧必须具有相同的色彩空间"));
+        }
+
+        let batch_size = frame_data.len();
+        let width = first_width;
+        let height = first_height;
+        let color_space = first.color_space;
+        let chroma = GpuChromaFormat::from_pixel(first.format)?;
+        let y_size = (width * height) as usize;
+        let uv_size = chroma.chroma_plane_size(y_size);
+        let frame_yuv_size = y_size + chroma.total_chroma_bytes(y_size);
+
+        for frame in frame_data {
+            if frame.data.len() < frame_yuv_size {
+                return Err(anyhow!("YUV数据长度不足"));
+            }
+        }
+
+        let evicted = if self.free_slots.is_empty() {
+            Some(self.drain_oldest().await?)
+        } else {
+            None
+        };
+
+        let batch_index = self.batch_counter;
+        self.batch_counter += 1;
+
+        let slot_idx = self.free_slots.pop().expect("刚drain过一个slot，free_slots不可能还是空的");
+        self.ensure_slot(slot_idx, batch_size, width, height, chroma, batch_index)?;
+
+        // 走staging belt而不是现攒Vec+queue.write_buffer：直接把每帧的平面数据写进
+        // 已经map好的显存里，省掉批次级临时Vec的分配和拷贝，以及write_buffer内部
+        // 再拷贝一次进wgpu自己staging区的开销。
+        let (u_offset, u_len, v_offset, v_len) = chroma.plane_spec(y_size);
+        let padded_y_len = pad_size((y_size * batch_size) as u64);
+        let padded_uv_len = pad_size((uv_size * batch_size) as u64);
+
+        let y_chunk = self.staging_belt.acquire(&self.device, padded_y_len);
+        let u_chunk = self.staging_belt.acquire(&self.device, padded_uv_len);
+        let v_chunk = self.staging_belt.acquire(&self.device, padded_uv_len);
+
+        fill_staging_chunk(&y_chunk, frame_data, 0, y_size, padded_y_len);
+        fill_staging_chunk(&u_chunk, frame_data, u_offset, u_len, padded_uv_len);
+        fill_staging_chunk(&v_chunk, frame_data, v_offset, v_len, padded_uv_len);
+
+        y_chunk.unmap();
+        u_chunk.unmap();
+        v_chunk.unmap();
+
+        let workgroup_x = (width + 15) / 16;
+        let workgroup_y = (height + 15) / 16;
+        let workgroup_z = batch_size as u32;
+
+        // 先把encoder拿出来，再借`slot`——`acquire_encoder`要整个`&mut self`，不能跟
+        // 下面借自`self.slots`的`slot`同时活着。
+        let mut encoder = self.acquire_encoder();
+
+        {
+            let slot = self.slots[slot_idx].as_ref().unwrap();
+            // 后4个字段是chunk3-6新增的：矩阵/量化范围/chroma布局/NV12-NV21交织顺序，
+            // 让shader能跟CPU路径的`convert_yuv_to_rgb_software`一样按需选系数和采样方式。
+            let params = [
+                width,
+                height,
+                (y_size * batch_size) as u32,
+                (uv_size * batch_size) as u32,
+                color_space.matrix.shader_tag(),
+                color_space.range.shader_tag(),
+                chroma.shader_tag(),
+                chroma.swapped_tag(),
+            ];
+            self.queue.write_buffer(&slot.params_buffer, 0, bytemuck::cast_slice(&params));
+
+            encoder.copy_buffer_to_buffer(&y_chunk, 0, &slot.y_buffer, 0, padded_y_len);
+            encoder.copy_buffer_to_buffer(&u_chunk, 0, &slot.u_buffer, 0, padded_uv_len);
+            encoder.copy_buffer_to_buffer(&v_chunk, 0, &slot.v_buffer, 0, padded_uv_len);
+
+            // 每个slot在共享`query_set`里占两个槽位（起始/结束），这样不同slot的
+            // compute pass可以各自独立计时，不会互相覆盖。
+            let timestamp_writes = self.query_set.as_ref().map(|query_set| wgpu::ComputePassTimestampWrites {
+                query_set,
+                beginning_of_pass_write_index: Some((slot_idx * 2) as u32),
+                end_of_pass_write_index: Some((slot_idx * 2 + 1) as u32),
+            });
+
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("GPU Compute Pass"),
+                    timestamp_writes,
+                });
+                compute_pass.set_pipeline(&self.gpu_pipeline);
+                compute_pass.set_bind_group(0, &slot.bind_group, &[]);
+                compute_pass.dispatch_workgroups(workgroup_x, workgroup_y, workgroup_z);
+            }
+
+            if let (Some(query_set), Some(timestamps)) = (&self.query_set, &slot.timestamps) {
+                encoder.resolve_query_set(
+                    query_set,
+                    (slot_idx * 2) as u32..(slot_idx * 2 + 2) as u32,
+                    &timestamps.resolve_buffer,
+                    0,
+                );
+                encoder.copy_buffer_to_buffer(&timestamps.resolve_buffer, 0, &timestamps.readback_buffer, 0, TIMESTAMP_QUERY_BYTES);
+            }
+
+            let total_output_size = (width * height * 4 * batch_size as u32) as u64;
+            encoder.copy_buffer_to_buffer(&slot.output_buffer, 0, &slot.read_buffer, 0, total_output_size);
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+        // 新建的encoder跟刚提交的那个是完全独立的对象，不需要等这次提交完成就能立刻
+        // 把备用encoder补回池子。
+        self.release_encoder();
+
+        let slot = self.slots[slot_idx].as_ref().unwrap();
+        let buffer_slice = slot.read_buffer.slice(..);
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        // 非阻塞地推进一下设备队列：如果更早批次的提交已经跑完了，它们`read_buffer`的
+        // map回调会在这里被触发，但不会因为这一批还没跑完而卡住。
+        let _ = self.device.poll(wgpu::MaintainBase::Poll);
+
+        self.in_flight.push_back(InFlightBatch {
+            slot: slot_idx,
+            batch_size,
+            width,
+            height,
+            y_chunk,
+            u_chunk,
+            v_chunk,
+            receiver,
+            batch_index,
+        });
+
+        Ok(evicted)
+    }
+
+    /// 等最早提交、还没读回的那个批次的`read_buffer`完成map，解包成RGB，然后把它的
+    /// staging chunk还给belt、slot还给空闲池。这是pipeline里真正会阻塞CPU的地方，
+    /// 调用者只应该在腾slot或者最后flush剩余批次时走到这里。
+    async fn drain_oldest(&mut self) -> Result<Vec<Vec<u8>>> {
+        let entry = self.in_flight.pop_front().expect("drain_oldest被调用时pipeline是空的");
+
+        let _ = self.device.poll(wgpu::MaintainBase::Wait);
+        entry.receiver.await.map_err(|_| anyhow!("无法映射缓冲区"))??;
+
+        let slot = self.slots[entry.slot].as_ref().unwrap();
+        let buffer_slice = slot.read_buffer.slice(..);
+        let data = buffer_slice.get_mapped_range();
+
+        let frame_rgb_size = (entry.width * entry.height * 3) as usize;
+        let mut results = Vec::with_capacity(entry.batch_size);
+
+        for frame_idx in 0..entry.batch_size {
+            let mut rgb_data = Vec::with_capacity(frame_rgb_size);
+            let frame_offset = frame_idx * (entry.width * entry.height * 4) as usize;
+
+            for pixel_idx in 0..(entry.width * entry.height) as usize {
+                let rgba_offset = frame_offset + pixel_idx * 4;
+                if rgba_offset + 3 < data.len() {
+                    let rgba_bytes = &data[rgba_offset..rgba_offset + 4];
+                    let rgba_u32 = u32::from_le_bytes([rgba_bytes[0], rgba_bytes[1], rgba_bytes[2], rgba_bytes[3]]);
+
+                    rgb_data.push((rgba_u32 & 0xFF) as u8);
+                    rgb_data.push(((rgba_u32 >> 8) & 0xFF) as u8);
+                    rgb_data.push(((rgba_u32 >> 16) & 0xFF) as u8);
+                }
+            }
+            results.push(rgb_data);
+        }
+
+        drop(data);
+        slot.read_buffer.unmap();
+
+        if let Some(timestamps) = &slot.timestamps {
+            // 这一步只在verbose时发生：此时GPU那批提交已经确认跑完（上面已经
+            // `poll(Wait)`过），所以map之后再`poll(Wait)`一次，oneshot channel里的
+            // 结果就已经就绪，后面的await不会真的挂起等待。
+            let ts_slice = timestamps.readback_buffer.slice(..);
+            let (ts_sender, ts_receiver) = futures::channel::oneshot::channel();
+            ts_slice.map_async(wgpu::MapMode::Read, move |result| {
+                let _ = ts_sender.send(result);
+            });
+            let _ = self.device.poll(wgpu::MaintainBase::Wait);
+            if ts_receiver.await.map_err(|_| anyhow!("无法映射时间戳缓冲区"))?.is_ok() {
+                let ts_data = ts_slice.get_mapped_range();
+                let start_ticks = u64::from_le_bytes(ts_data[0..8].try_into().unwrap());
+                let end_ticks = u64::from_le_bytes(ts_data[8..16].try_into().unwrap());
+                drop(ts_data);
+                let gpu_time_ns = end_ticks.saturating_sub(start_ticks) as f64 * self.timestamp_period_ns as f64;
+                println!(
+                    "GPU批次 #{} ({}x{}, {}帧): compute耗时 {:.3}ms",
+                    entry.batch_index, entry.width, entry.height, entry.batch_size, gpu_time_ns / 1_000_000.0
+                );
+            }
+            timestamps.readback_buffer.unmap();
+        }
+
+        // 这批对应的提交（含三个copy_buffer_to_buffer）已经确认跑完了，chunk可以
+        // 安全地还给belt供下一批复用。
+        self.staging_belt.recycle(&self.device, entry.y_chunk);
+        self.staging_belt.recycle(&self.device, entry.u_chunk);
+        self.staging_belt.recycle(&self.device, entry.v_chunk);
+        self.free_slots.push(entry.slot);
+
+        Ok(results)
+    }
+
+    fn has_in_flight(&self) -> bool {
+        !self.in_flight.is_empty()
+    }
+}
+
+#[cfg(feature = "wgpu-mode")]
+fn pad_size(size: u64) -> u64 {
+    (size + 3) & !3
+}
@@ -1,66 +1,60 @@
 use anyhow::Result;
-use crate::converter::{YuvToRgbConverter, FrameData, ConversionMode};
+use crate::converters::{build_worker_pool, convert_yuv_to_rgb_software, pack_channels, resize_rgb, OutputFormat, ScaleTarget, YuvToRgbConverter, FrameData};
 
 /// 手工实现的YUV转换器
-/// 
-/// 使用手工实现的YUV420P到RGB转换算法
-/// 主要用于教育目的和理解转换原理
-pub struct ManualConverter;
+///
+/// 使用手工实现的YUV到RGB转换算法，Kr/Kb系数和量化范围都取自
+/// `frame_data.color_space`（而不是写死BT.601），主要用于教育目的和理解转换原理。
+/// 支持YUV420P（平面4:2:0）、NV12/NV21（半平面4:2:0，UV交织，顺序相反）
+/// 和YUV422P（平面4:2:2，色度只在水平方向采样）。逐像素转换按行拆给`worker_threads`
+/// 个线程并行跑，默认吃满所有CPU核心。线程池在`set_worker_threads`时构建一次，
+/// 跨帧复用，不是每帧现造一个。
+pub struct ManualConverter {
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
+    pool: rayon::ThreadPool,
+}
 
 impl ManualConverter {
     pub fn new() -> Self {
-        Self
+        Self {
+            scale_target: None,
+            output_format: OutputFormat::default(),
+            pool: build_worker_pool(1).expect("failed to build default rayon thread pool"),
+        }
+    }
+}
+
+impl Default for ManualConverter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[async_trait::async_trait(?Send)]
 impl YuvToRgbConverter for ManualConverter {
     async fn convert(&mut self, frame_data: &FrameData) -> Result<Vec<u8>> {
-        if frame_data.format != ffmpeg_next::util::format::Pixel::YUV420P {
-            anyhow::bail!("Manual converter only supports YUV420P format");
-        }
-        
-        let width = frame_data.width as i32;
-        let height = frame_data.height as i32;
-        let y_size = (width * height) as usize;
-        let uv_size = y_size / 4;
-        
-        if frame_data.data.len() < y_size + 2 * uv_size {
-            anyhow::bail!("Invalid YUV data size");
-        }
-        
-        // 简单的YUV420P到RGB转换（手工实现）
-        let y_plane = &frame_data.data[0..y_size];
-        let u_plane = &frame_data.data[y_size..y_size + uv_size];
-        let v_plane = &frame_data.data[y_size + uv_size..y_size + 2 * uv_size];
-        
-        let mut rgb_data = vec![0u8; (width * height * 3) as usize];
-        
-        for y in 0..height {
-            for x in 0..width {
-                let y_idx = (y * width + x) as usize;
-                let uv_idx = ((y / 2) * (width / 2) + (x / 2)) as usize;
-                
-                let y_val = y_plane[y_idx] as f32;
-                let u_val = u_plane[uv_idx] as f32 - 128.0;
-                let v_val = v_plane[uv_idx] as f32 - 128.0;
-                
-                // YUV到RGB转换公式 (ITU-R BT.601标准)
-                let r = (y_val + 1.402 * v_val).clamp(0.0, 255.0) as u8;
-                let g = (y_val - 0.344136 * u_val - 0.714136 * v_val).clamp(0.0, 255.0) as u8;
-                let b = (y_val + 1.772 * u_val).clamp(0.0, 255.0) as u8;
-                
-                let rgb_idx = (y * width + x) as usize * 3;
-                rgb_data[rgb_idx] = r;
-                rgb_data[rgb_idx + 1] = g;
-                rgb_data[rgb_idx + 2] = b;
-            }
-        }
-        
-        Ok(rgb_data)
+        let rgb_data = convert_yuv_to_rgb_software(frame_data, &self.pool)?;
+
+        let rgb_data = match self.scale_target {
+            Some(target) => resize_rgb(&rgb_data, frame_data.width, frame_data.height, target.width, target.height, target.filter, 3),
+            None => rgb_data,
+        };
+
+        Ok(pack_channels(&rgb_data, self.output_format))
+    }
+
+    fn set_scale_target(&mut self, target: Option<ScaleTarget>) {
+        self.scale_target = target;
     }
 
-    fn get_mode(&self) -> ConversionMode {
-        ConversionMode::Manual
+    fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
     }
-} 
\ No newline at end of file
+
+    fn set_worker_threads(&mut self, threads: usize) {
+        if let Ok(pool) = build_worker_pool(threads) {
+            self.pool = pool;
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use anyhow::Result;
+use rayon::prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ConversionMode {
     FFmpeg,
-    OpenCV, 
+    OpenCV,
     Manual,
     WGPU,
     Yuvutils,
+    Libyuv,
 }
 
 impl ConversionMode {
@@ -18,6 +20,7 @@ impl ConversionMode {
             ConversionMode::WGPU => "wgpu",
 
             ConversionMode::Yuvutils => "yuvutils",
+            ConversionMode::Libyuv => "libyuv",
         }
     }
 
@@ -29,6 +32,230 @@ impl ConversionMode {
             ConversionMode::WGPU => "使用WGPU进行GPU加速转换",
 
             ConversionMode::Yuvutils => "使用yuvutils库进行SIMD优化转换",
+            ConversionMode::Libyuv => "使用libyuv库进行单次调用CPU转换",
+        }
+    }
+}
+
+/// YUV<->RGB转换矩阵，对应ITU-R的三套常见标准。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMatrix {
+    Bt601,
+    Bt709,
+    Bt2020,
+}
+
+impl ColorMatrix {
+    /// 对应矩阵的Kr/Kb常数，用于推导YUV<->RGB转换系数。
+    pub fn kr_kb(&self) -> (f32, f32) {
+        match self {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+            ColorMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+
+    /// 编码进GPU批处理params uniform里的matrix selector，取值跟shader侧的switch分支
+    /// 一一对应；CPU路径直接用`kr_kb()`算系数，不需要这个。
+    pub fn shader_tag(&self) -> u32 {
+        match self {
+            ColorMatrix::Bt601 => 0,
+            ColorMatrix::Bt709 => 1,
+            ColorMatrix::Bt2020 => 2,
+        }
+    }
+}
+
+/// YUV采样的量化范围：limited是常见的studio-swing（亮度16-235，色度16-240），
+/// full则是0-255满摆幅（比如从截屏/录屏软件生成的码流常见）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRange {
+    Limited,
+    Full,
+}
+
+impl ColorRange {
+    /// 编码进GPU批处理params uniform里的range selector，取值跟shader侧的switch分支
+    /// 一一对应；CPU路径直接用下面的`y_offset/y_scale/uv_scale`常量表，不需要这个。
+    pub fn shader_tag(&self) -> u32 {
+        match self {
+            ColorRange::Limited => 0,
+            ColorRange::Full => 1,
+        }
+    }
+}
+
+/// 一帧的颜色空间描述：矩阵系数 + 量化范围。
+///
+/// 默认回退到BT.709 limited——大多数HD/UHD流都是这个，比history遗留的BT.601更合适。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSpace {
+    pub matrix: ColorMatrix,
+    pub range: ColorRange,
+}
+
+impl ColorSpace {
+    pub fn bt709_limited() -> Self {
+        Self { matrix: ColorMatrix::Bt709, range: ColorRange::Limited }
+    }
+
+    pub fn bt601_limited() -> Self {
+        Self { matrix: ColorMatrix::Bt601, range: ColorRange::Limited }
+    }
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        Self::bt709_limited()
+    }
+}
+
+/// 输出缩放用的重采样算法，映射到FFmpeg SWScale的对应flag；
+/// CPU（manual/yuvutils/wgpu）后端没有真正的双三次实现，退化成双线性。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ScaleFilter {
+    Point,
+    Bilinear,
+    Bicubic,
+}
+
+/// 转换流水线输出的目标尺寸与重采样算法，`None`表示保持源分辨率不缩放。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleTarget {
+    pub width: u32,
+    pub height: u32,
+    pub filter: ScaleFilter,
+}
+
+/// 把`height`均分给`threads`个worker，再向上取整到`row_align`的倍数，得到每个worker
+/// 负责的行数。4:2:0格式（YUV420P/NV12/NV21）色度垂直方向隔行采样，区块必须是偶数行，
+/// 否则某块会在半条色度行中间截断；4:2:2色度和亮度同高，`row_align`传1即可。
+pub(crate) fn chunk_rows(height: u32, threads: usize, row_align: u32) -> u32 {
+    let threads = threads.max(1) as u32;
+    let row_align = row_align.max(1);
+    let rows = (height + threads - 1) / threads;
+    (((rows + row_align - 1) / row_align) * row_align).max(row_align)
+}
+
+/// 为CPU转换器构建一次性复用的rayon线程池，由converter持有并跨帧复用，而不是
+/// 每个`convert()`调用都现造一个——否则`worker_threads`这个旋钮形同虚设，每帧都在
+/// 付线程池创建/销毁的开销。`threads`为0时按1个线程处理，跟旧版`.max(1)`语义一致。
+pub(crate) fn build_worker_pool(threads: usize) -> Result<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads.max(1))
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build rayon thread pool: {}", e))
+}
+
+/// 最近邻/双线性CPU缩放，给不经过FFmpeg SWScale的后端（manual/yuvutils/wgpu）用。
+/// `ScaleFilter::Bicubic`在这里按双线性处理。`channels`是每像素字节数（3=RGB/BGR，
+/// 4=RGBA/BGRA）——缩放只是几何重采样，不关心通道的具体顺序，所以可以在`pack_channels`
+/// 之前或之后调用，只要传对通道数即可。
+pub(crate) fn resize_rgb(rgb: &[u8], src_w: u32, src_h: u32, dst_w: u32, dst_h: u32, filter: ScaleFilter, channels: usize) -> Vec<u8> {
+    if src_w == dst_w && src_h == dst_h {
+        return rgb.to_vec();
+    }
+
+    let (src_w, src_h, dst_w, dst_h) = (src_w as usize, src_h as usize, dst_w as usize, dst_h as usize);
+    let mut out = vec![0u8; dst_w * dst_h * channels];
+
+    let x_ratio = src_w as f32 / dst_w as f32;
+    let y_ratio = src_h as f32 / dst_h as f32;
+
+    for dy in 0..dst_h {
+        for dx in 0..dst_w {
+            let out_idx = (dy * dst_w + dx) * channels;
+
+            match filter {
+                ScaleFilter::Point => {
+                    let sx = ((dx as f32 * x_ratio) as usize).min(src_w - 1);
+                    let sy = ((dy as f32 * y_ratio) as usize).min(src_h - 1);
+                    let src_idx = (sy * src_w + sx) * channels;
+                    out[out_idx..out_idx + channels].copy_from_slice(&rgb[src_idx..src_idx + channels]);
+                }
+                ScaleFilter::Bilinear | ScaleFilter::Bicubic => {
+                    let sx = (dx as f32 + 0.5) * x_ratio - 0.5;
+                    let sy = (dy as f32 + 0.5) * y_ratio - 0.5;
+                    let sx0 = sx.floor().max(0.0) as usize;
+                    let sy0 = sy.floor().max(0.0) as usize;
+                    let sx1 = (sx0 + 1).min(src_w - 1);
+                    let sy1 = (sy0 + 1).min(src_h - 1);
+                    let fx = (sx - sx0 as f32).clamp(0.0, 1.0);
+                    let fy = (sy - sy0 as f32).clamp(0.0, 1.0);
+
+                    for c in 0..channels {
+                        let p00 = rgb[(sy0 * src_w + sx0) * channels + c] as f32;
+                        let p10 = rgb[(sy0 * src_w + sx1) * channels + c] as f32;
+                        let p01 = rgb[(sy1 * src_w + sx0) * channels + c] as f32;
+                        let p11 = rgb[(sy1 * src_w + sx1) * channels + c] as f32;
+                        let top = p00 * (1.0 - fx) + p10 * fx;
+                        let bottom = p01 * (1.0 - fx) + p11 * fx;
+                        out[out_idx + c] = (top * (1.0 - fy) + bottom * fy).round().clamp(0.0, 255.0) as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// 转换器输出的像素布局。默认是3通道RGB；很多下游消费者（OpenCV的`Mat`、GPU贴图、
+/// Core Graphics）要的是BGRA/RGBA，这样就不用每次都再套一遍单独的swizzle。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Rgb,
+    Bgr,
+    Rgba,
+    Bgra,
+}
+
+impl OutputFormat {
+    pub fn channels(&self) -> usize {
+        match self {
+            OutputFormat::Rgb | OutputFormat::Bgr => 3,
+            OutputFormat::Rgba | OutputFormat::Bgra => 4,
+        }
+    }
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Rgb
+    }
+}
+
+/// 把一段packed RGB24结果按需重排成目标格式。manual、opencv、wgpu的CPU后处理完全没有
+/// 原生4通道/BGR入口，统一走这里兜底；yuvutils/libyuv对各自能直连的格式（各转换器里
+/// 细节不同）会跳过这个函数，省一次额外拷贝。FFmpeg SWScale能把四种格式都直接喂给
+/// scaler产出，从不需要走这里。
+pub(crate) fn pack_channels(rgb: &[u8], format: OutputFormat) -> Vec<u8> {
+    match format {
+        OutputFormat::Rgb => rgb.to_vec(),
+        OutputFormat::Bgr => {
+            let mut out = rgb.to_vec();
+            for px in out.chunks_exact_mut(3) {
+                px.swap(0, 2);
+            }
+            out
+        }
+        OutputFormat::Rgba => {
+            let mut out = Vec::with_capacity(rgb.len() / 3 * 4);
+            for px in rgb.chunks_exact(3) {
+                out.extend_from_slice(px);
+                out.push(255);
+            }
+            out
+        }
+        OutputFormat::Bgra => {
+            let mut out = Vec::with_capacity(rgb.len() / 3 * 4);
+            for px in rgb.chunks_exact(3) {
+                out.push(px[2]);
+                out.push(px[1]);
+                out.push(px[0]);
+                out.push(255);
+            }
+            out
         }
     }
 }
@@ -40,6 +267,7 @@ pub struct FrameData {
     pub height: u32,
     pub data: Vec<u8>,
     pub format: ffmpeg_next::util::format::Pixel,
+    pub color_space: ColorSpace,
 }
 
 #[derive(Debug, Clone)]
@@ -49,6 +277,7 @@ pub struct ChannelFrameData {
     pub height: u32,
     pub yuv_data: Vec<u8>,
     pub format: ffmpeg_next::util::format::Pixel,
+    pub color_space: ColorSpace,
 }
 
 impl From<FrameData> for ChannelFrameData {
@@ -59,6 +288,7 @@ impl From<FrameData> for ChannelFrameData {
             height: frame_data.height,
             yuv_data: frame_data.data,
             format: frame_data.format,
+            color_space: frame_data.color_space,
         }
     }
 }
@@ -71,82 +301,343 @@ impl From<ChannelFrameData> for FrameData {
             height: channel_frame_data.height,
             data: channel_frame_data.yuv_data,
             format: channel_frame_data.format,
+            color_space: channel_frame_data.color_space,
+        }
+    }
+}
+
+/// 某个格式下、给定像素坐标应该去哪取(U, V)样本。
+/// 把"怎么布局色度平面"和下面的矩阵运算彻底分开，新增格式只需要加一个分支。
+enum ChromaLayout<'a> {
+    /// 平面分离：YUV420P是(width/2, height/2)，YUV422P是(width/2, height)
+    Planar { u_plane: &'a [u8], v_plane: &'a [u8], chroma_width: i32, chroma_height: i32 },
+    /// 半平面交织：NV12是UV顺序，NV21是VU顺序，色度平面始终是(width/2, height/2)对
+    SemiPlanar { uv_plane: &'a [u8], chroma_width: i32, chroma_height: i32, swapped: bool },
+}
+
+impl<'a> ChromaLayout<'a> {
+    fn sample(&self, x: i32, y: i32, width: i32, height: i32) -> (u8, u8) {
+        match self {
+            ChromaLayout::Planar { u_plane, v_plane, chroma_width, chroma_height } => {
+                let cx = x * chroma_width / width;
+                let cy = y * chroma_height / height;
+                let idx = (cy * chroma_width + cx) as usize;
+                (u_plane[idx], v_plane[idx])
+            }
+            ChromaLayout::SemiPlanar { uv_plane, chroma_width, chroma_height, swapped } => {
+                let cx = x * chroma_width / width;
+                let cy = y * chroma_height / height;
+                let idx = ((cy * chroma_width + cx) * 2) as usize;
+                let (a, b) = (uv_plane[idx], uv_plane[idx + 1]);
+                if *swapped { (b, a) } else { (a, b) }
+            }
+        }
+    }
+}
+
+/// 纯软件的YUV->RGB逐像素实现，系数和量化范围都取自`frame_data.color_space`。
+/// 支持YUV420P/YUV422P/YUV444P（平面）和NV12/NV21（半平面），给不支持任意色彩矩阵的
+/// 后端（比如OpenCV的内置`cvtColor`代码只认BT.601）用作精确回退路径。
+///
+/// 每个像素的计算只依赖自己所在行（`ChromaLayout::sample`按比例取色度行，不跨行
+/// 读写），天然是行级并行的——按`worker_threads`把输出行切成不相交的区块扔给
+/// rayon线程池，每个线程只写自己那一块，不需要锁。
+pub(crate) fn convert_yuv_to_rgb_software(frame_data: &FrameData, pool: &rayon::ThreadPool) -> Result<Vec<u8>> {
+    use ffmpeg_next::util::format::Pixel;
+
+    let width = frame_data.width as i32;
+    let height = frame_data.height as i32;
+    let y_size = (width * height) as usize;
+    let data = &frame_data.data;
+
+    let layout = match frame_data.format {
+        Pixel::YUV420P => {
+            let uv_size = y_size / 4;
+            if data.len() < y_size + 2 * uv_size {
+                anyhow::bail!("Invalid YUV420P data size");
+            }
+            ChromaLayout::Planar {
+                u_plane: &data[y_size..y_size + uv_size],
+                v_plane: &data[y_size + uv_size..y_size + 2 * uv_size],
+                chroma_width: width / 2,
+                chroma_height: height / 2,
+            }
+        }
+        Pixel::YUV422P => {
+            let uv_size = (width / 2 * height) as usize;
+            if data.len() < y_size + 2 * uv_size {
+                anyhow::bail!("Invalid YUV422P data size");
+            }
+            ChromaLayout::Planar {
+                u_plane: &data[y_size..y_size + uv_size],
+                v_plane: &data[y_size + uv_size..y_size + 2 * uv_size],
+                chroma_width: width / 2,
+                chroma_height: height,
+            }
+        }
+        Pixel::NV12 | Pixel::NV21 => {
+            let uv_size = (width / 2 * height / 2 * 2) as usize;
+            if data.len() < y_size + uv_size {
+                anyhow::bail!("Invalid NV12/NV21 data size");
+            }
+            ChromaLayout::SemiPlanar {
+                uv_plane: &data[y_size..y_size + uv_size],
+                chroma_width: width / 2,
+                chroma_height: height / 2,
+                swapped: frame_data.format == Pixel::NV21,
+            }
+        }
+        Pixel::YUV444P => {
+            let uv_size = y_size;
+            if data.len() < y_size + 2 * uv_size {
+                anyhow::bail!("Invalid YUV444P data size");
+            }
+            ChromaLayout::Planar {
+                u_plane: &data[y_size..y_size + uv_size],
+                v_plane: &data[y_size + uv_size..y_size + 2 * uv_size],
+                chroma_width: width,
+                chroma_height: height,
+            }
+        }
+        other => anyhow::bail!("Software YUV->RGB conversion does not support {:?} format", other),
+    };
+
+    let y_plane = &data[0..y_size];
+    let mut rgb_data = vec![0u8; (width * height * 3) as usize];
+
+    let ColorSpace { matrix, range } = frame_data.color_space;
+    let (kr, kb) = matrix.kr_kb();
+    let kg = 1.0 - kr - kb;
+
+    // limited range下亮度摆幅是16-235、色度是16-240，先展开回满摆幅再套矩阵；
+    // full range直接用，不需要额外缩放。
+    let (y_offset, y_scale, uv_scale) = match range {
+        ColorRange::Limited => (16.0f32, 255.0 / 219.0, 255.0 / 224.0),
+        ColorRange::Full => (0.0f32, 1.0, 1.0),
+    };
+
+    let threads = pool.current_num_threads();
+    let rows_per_chunk = ((height as usize) + threads - 1) / threads;
+    let row_bytes = width as usize * 3;
+    let chunk_bytes = row_bytes * rows_per_chunk.max(1);
+
+    pool.install(|| {
+        rgb_data.par_chunks_mut(chunk_bytes.max(row_bytes)).enumerate().for_each(|(chunk_idx, chunk)| {
+            let row_start = chunk_idx * rows_per_chunk;
+            let rows_in_chunk = chunk.len() / row_bytes;
+
+            for local_y in 0..rows_in_chunk {
+                let y = (row_start + local_y) as i32;
+                for x in 0..width {
+                    let y_idx = (y * width + x) as usize;
+                    let (u_sample, v_sample) = layout.sample(x, y, width, height);
+
+                    let y_val = (y_plane[y_idx] as f32 - y_offset) * y_scale;
+                    let u_val = (u_sample as f32 - 128.0) * uv_scale;
+                    let v_val = (v_sample as f32 - 128.0) * uv_scale;
+
+                    // YUV到RGB转换公式，Kr/Kb由`color_space.matrix`决定(BT.601/709/2020通用推导)
+                    let r = (y_val + 2.0 * (1.0 - kr) * v_val).clamp(0.0, 255.0) as u8;
+                    let g = (y_val - 2.0 * (1.0 - kb) * kb / kg * u_val - 2.0 * (1.0 - kr) * kr / kg * v_val)
+                        .clamp(0.0, 255.0) as u8;
+                    let b = (y_val + 2.0 * (1.0 - kb) * u_val).clamp(0.0, 255.0) as u8;
+
+                    let rgb_idx = local_y * row_bytes + x as usize * 3;
+                    chunk[rgb_idx] = r;
+                    chunk[rgb_idx + 1] = g;
+                    chunk[rgb_idx + 2] = b;
+                }
+            }
+        });
+    });
+
+    Ok(rgb_data)
+}
+
+/// contact sheet的网格布局：把`rows*cols`帧拼成一张mosaic图，而不是一帧一个文件。
+/// `padding`是每格之间留的黑边像素宽度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridLayout {
+    pub rows: u32,
+    pub cols: u32,
+    pub padding: u32,
+}
+
+/// 把一组同尺寸的packed像素缓冲区按`layout`拼成一张更大的contact sheet，多出的格子
+/// 留黑。跟`resize_rgb`一样不关心通道顺序，只要传对`channels`即可；超过`rows*cols`的
+/// 帧会被忽略——调用方负责按网格大小分批传入。
+pub(crate) fn compose_contact_sheet(cells: &[Vec<u8>], cell_w: u32, cell_h: u32, layout: GridLayout, channels: usize) -> (Vec<u8>, u32, u32) {
+    let GridLayout { rows, cols, padding } = layout;
+    let sheet_w = cols * cell_w + (cols + 1) * padding;
+    let sheet_h = rows * cell_h + (rows + 1) * padding;
+    let mut out = vec![0u8; (sheet_w * sheet_h) as usize * channels];
+
+    for (idx, cell) in cells.iter().enumerate().take((rows * cols) as usize) {
+        let row = idx as u32 / cols;
+        let col = idx as u32 % cols;
+        let dst_x0 = padding + col * (cell_w + padding);
+        let dst_y0 = padding + row * (cell_h + padding);
+
+        for y in 0..cell_h {
+            let src_row_start = (y * cell_w) as usize * channels;
+            let src_row = &cell[src_row_start..src_row_start + cell_w as usize * channels];
+            let dst_row_start = ((dst_y0 + y) * sheet_w + dst_x0) as usize * channels;
+            out[dst_row_start..dst_row_start + src_row.len()].copy_from_slice(src_row);
+        }
+    }
+
+    (out, sheet_w, sheet_h)
+}
+
+/// 累积`process_frame_with_mode`产出的帧，凑满一块`GridLayout`就拼成一张contact sheet
+/// 存盘再清空，继续凑下一块；处理结束时可能还剩不满一块的尾巴，由调用方`flush`。
+struct ContactSheetAccumulator {
+    layout: GridLayout,
+    output_format: OutputFormat,
+    output_dir: String,
+    mode_str: &'static str,
+    cells: Vec<Vec<u8>>,
+    cell_size: Option<(u32, u32)>,
+    sheet_index: u32,
+}
+
+impl ContactSheetAccumulator {
+    fn new(layout: GridLayout, output_format: OutputFormat, output_dir: String, mode_str: &'static str) -> Self {
+        Self { layout, output_format, output_dir, mode_str, cells: Vec::new(), cell_size: None, sheet_index: 0 }
+    }
+
+    fn push(&mut self, pixel_data: Vec<u8>, width: u32, height: u32) -> Result<()> {
+        self.cell_size = Some((width, height));
+        self.cells.push(pixel_data);
+        if self.cells.len() == (self.layout.rows * self.layout.cols) as usize {
+            self.flush()?;
         }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.cells.is_empty() {
+            return Ok(());
+        }
+        let (cell_w, cell_h) = self.cell_size.unwrap();
+        let (sheet_data, sheet_w, sheet_h) = compose_contact_sheet(&self.cells, cell_w, cell_h, self.layout, self.output_format.channels());
+        let filename = format!("{}/contact_sheet_{}_{:04}.jpg", self.output_dir, self.mode_str, self.sheet_index);
+        save_pixels_as_image(&filename, sheet_w, sheet_h, sheet_data, self.output_format)?;
+        self.sheet_index += 1;
+        self.cells.clear();
+        Ok(())
     }
 }
 
+/// 把转换结果存成图片文件。`image` crate本身没有Bgr/Bgra像素类型，只有Rgb/Rgba，
+/// 所以Bgr按3通道Rgb缓冲区存、Bgra按4通道Rgba缓冲区存——通道数对、字节顺序不对，
+/// 存出来的jpg/png会红蓝颠倒。这个限制只影响"存文件"这一步，`convert()`返回给调用方
+/// 的原始字节顺序是对的，GPU贴图/OpenCV Mat等直接消费字节的场景不受影响。
+fn save_pixels_as_image(filename: &str, width: u32, height: u32, data: Vec<u8>, format: OutputFormat) -> Result<()> {
+    use image::{ImageBuffer, Rgb, Rgba};
+
+    match format.channels() {
+        4 => {
+            let img = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data)
+                .ok_or_else(|| anyhow::anyhow!("无法创建图像缓冲区"))?;
+            img.save(filename)?;
+        }
+        _ => {
+            let img = ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, data)
+                .ok_or_else(|| anyhow::anyhow!("无法创建图像缓冲区"))?;
+            img.save(filename)?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn process_frame_with_mode(
     mut receiver: tokio::sync::mpsc::Receiver<ChannelFrameData>,
     mode: ConversionMode,
     output_dir: Option<String>,
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
+    grid_layout: Option<GridLayout>,
+    worker_threads: usize,
+    verbose: bool,
 ) -> Result<u32> {
     use std::fs;
-    use image::{ImageBuffer, Rgb};
-    
+
     if let Some(ref output_dir) = output_dir {
         fs::create_dir_all(output_dir)?;
     }
-    
+
+    let mut contact_sheet = match (grid_layout, &output_dir) {
+        (Some(layout), Some(output_dir)) => Some(ContactSheetAccumulator::new(layout, output_format, output_dir.clone(), mode.as_str())),
+        _ => None,
+    };
+
     let mut processed_count = 0u32;
-    
+
     // 特殊处理WGPU模式 - 使用批处理
     if mode == ConversionMode::WGPU {
         let mut frame_batch = Vec::new();
-        
+
         // 🎯 简化配置：使用固定的大批次，让流式系统自动处理分批
         const TARGET_BATCH_SIZE: usize = 64; // 固定使用64帧目标批次
-        
+
         let mut current_batch_size = 0;
-        
+
         while let Some(channel_frame) = receiver.recv().await {
             let frame_data: FrameData = channel_frame.into();
-            
+
             // 🎯 简化配置：直接使用目标批次大小
             if current_batch_size == 0 {
                 current_batch_size = TARGET_BATCH_SIZE;
-                println!("🚀 [简化批处理] 目标批次: {} 帧 ({}x{} 分辨率) - 流式系统自动分批", 
+                println!("🚀 [简化批处理] 目标批次: {} 帧 ({}x{} 分辨率) - 流式系统自动分批",
                         TARGET_BATCH_SIZE, frame_data.width, frame_data.height);
             }
-            
+
             frame_batch.push(frame_data);
-            
+
             // 当批次满了时处理批次
             if frame_batch.len() >= current_batch_size {
-                let batch_results = process_frame_batch(&frame_batch, &output_dir, &mode).await?;
+                let batch_results = process_frame_batch(&frame_batch, &output_dir, &mode, scale_target, output_format, contact_sheet.as_mut(), verbose).await?;
                 processed_count += batch_results;
                 frame_batch.clear();
             }
         }
-        
+
         // 处理剩余的帧
         if !frame_batch.is_empty() {
-            let batch_results = process_frame_batch(&frame_batch, &output_dir, &mode).await?;
+            let batch_results = process_frame_batch(&frame_batch, &output_dir, &mode, scale_target, output_format, contact_sheet.as_mut(), verbose).await?;
             processed_count += batch_results;
         }
     } else {
         // 原始逐帧处理逻辑
-        let mut converter = ConverterFactory::create_converter(mode).await?;
-        
+        let mut converter = ConverterFactory::create_converter(mode, verbose).await?;
+        converter.set_scale_target(scale_target);
+        converter.set_output_format(output_format);
+        converter.set_worker_threads(worker_threads);
+
         while let Some(channel_frame) = receiver.recv().await {
             let frame_data: FrameData = channel_frame.into();
-            
+
             match converter.convert(&frame_data).await {
-                Ok(rgb_data) => {
+                Ok(pixel_data) => {
                     if let Some(ref output_dir) = output_dir {
-                        let img = ImageBuffer::<Rgb<u8>, _>::from_raw(
-                            frame_data.width,
-                            frame_data.height,
-                            rgb_data,
-                        ).ok_or_else(|| anyhow::anyhow!("无法创建图像缓冲区"))?;
-
-                        let filename = format!(
-                            "{}/frame_{}_{:04}.jpg",
-                            output_dir,
-                            mode.as_str(),
-                            frame_data.frame_number
-                        );
-                        
-                        img.save(&filename)?;
+                        let (out_width, out_height) = scale_target
+                            .map(|t| (t.width, t.height))
+                            .unwrap_or((frame_data.width, frame_data.height));
+
+                        match contact_sheet.as_mut() {
+                            Some(sheet) => sheet.push(pixel_data, out_width, out_height)?,
+                            None => {
+                                let filename = format!(
+                                    "{}/frame_{}_{:04}.jpg",
+                                    output_dir,
+                                    mode.as_str(),
+                                    frame_data.frame_number
+                                );
+
+                                save_pixels_as_image(&filename, out_width, out_height, pixel_data, output_format)?;
+                            }
+                        }
                     }
                     processed_count += 1;
                 }
@@ -155,10 +646,14 @@ pub async fn process_frame_with_mode(
                 }
             }
         }
-        
+
         converter.cleanup().await?;
     }
-    
+
+    if let Some(mut sheet) = contact_sheet {
+        sheet.flush()?;
+    }
+
     Ok(processed_count)
 }
 
@@ -167,46 +662,65 @@ async fn process_frame_batch(
     frame_batch: &[FrameData],
     output_dir: &Option<String>,
     mode: &ConversionMode,
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
+    mut contact_sheet: Option<&mut ContactSheetAccumulator>,
+    verbose: bool,
 ) -> Result<u32> {
-    use image::{ImageBuffer, Rgb};
-    
     if frame_batch.is_empty() {
         return Ok(0);
     }
-    
+
     // 创建GPU处理器并直接调用批处理方法
-    let mut processor = crate::converters::wgpu_converter::GpuImageProcessor::new().await?;
-    
+    let mut processor = crate::converters::wgpu_converter::GpuImageProcessor::new(verbose).await?;
+
     // 准备批处理数据
-    let batch_data: Vec<(Vec<u8>, u32, u32)> = frame_batch
+    let batch_data: Vec<crate::converters::gpu_backend::BatchFrameData> = frame_batch
         .iter()
-        .map(|frame| (frame.data.clone(), frame.width, frame.height))
+        .map(|frame| crate::converters::gpu_backend::BatchFrameData {
+            width: frame.width,
+            height: frame.height,
+            data: frame.data.clone(),
+            format: frame.format,
+            color_space: frame.color_space,
+        })
         .collect();
-    
+
     // 🚀 执行GPU批处理转换
-    let batch_results = processor.convert_yuv420p_to_rgb(&batch_data).await?;
-    
+    let batch_results = processor.convert_batch_to_rgb(&batch_data).await?;
+
     // 保存结果
     for (frame_idx, rgb_data) in batch_results.iter().enumerate() {
         if let Some(ref output_dir) = output_dir {
             let frame = &frame_batch[frame_idx];
-            let img = ImageBuffer::<Rgb<u8>, _>::from_raw(
-                frame.width,
-                frame.height,
-                rgb_data.clone(),
-            ).ok_or_else(|| anyhow::anyhow!("无法创建图像缓冲区"))?;
-
-            let filename = format!(
-                "{}/frame_{}_{:04}.jpg",
-                output_dir,
-                mode.as_str(),
-                frame.frame_number
-            );
-            
-            img.save(&filename)?;
+
+            // 着色器本身还不会做缩放/换通道：GPU转换结果先在CPU上resize，再pack成目标格式
+            let (out_width, out_height, rgb_data) = match scale_target {
+                Some(target) => (
+                    target.width,
+                    target.height,
+                    resize_rgb(rgb_data, frame.width, frame.height, target.width, target.height, target.filter, 3),
+                ),
+                None => (frame.width, frame.height, rgb_data.clone()),
+            };
+            let pixel_data = pack_channels(&rgb_data, output_format);
+
+            match contact_sheet.as_deref_mut() {
+                Some(sheet) => sheet.push(pixel_data, out_width, out_height)?,
+                None => {
+                    let filename = format!(
+                        "{}/frame_{}_{:04}.jpg",
+                        output_dir,
+                        mode.as_str(),
+                        frame.frame_number
+                    );
+
+                    save_pixels_as_image(&filename, out_width, out_height, pixel_data, output_format)?;
+                }
+            }
         }
     }
-    
+
     Ok(batch_results.len() as u32)
 }
 
@@ -214,12 +728,19 @@ async fn process_frame_batch(
 pub trait YuvToRgbConverter {
     async fn convert(&mut self, frame_data: &FrameData) -> Result<Vec<u8>>;
     async fn cleanup(&mut self) -> Result<()> { Ok(()) }
+    /// 设置输出缩放目标；`None`恢复成按源分辨率输出。默认忽略——不是所有后端都实现了。
+    fn set_scale_target(&mut self, _target: Option<ScaleTarget>) {}
+    /// 设置输出像素布局（RGB/BGR/RGBA/BGRA）。默认忽略——等价于`OutputFormat::Rgb`。
+    fn set_output_format(&mut self, _format: OutputFormat) {}
+    /// 设置CPU后端单帧内部按行拆分的worker线程数（类似dav1d的`n_threads`）。
+    /// 默认忽略——FFmpeg SWScale/WGPU走各自的并行路径，不需要这个旋钮。
+    fn set_worker_threads(&mut self, _threads: usize) {}
 }
 
 pub struct ConverterFactory;
 
 impl ConverterFactory {
-    pub async fn create_converter(mode: ConversionMode) -> Result<Box<dyn YuvToRgbConverter>> {
+    pub async fn create_converter(mode: ConversionMode, verbose: bool) -> Result<Box<dyn YuvToRgbConverter>> {
         match mode {
             ConversionMode::FFmpeg => {
                 Ok(Box::new(crate::converters::ffmpeg_converter::FfmpegConverter::new()))
@@ -231,27 +752,46 @@ impl ConverterFactory {
                 Ok(Box::new(crate::converters::manual_converter::ManualConverter::new()))
             }
             ConversionMode::WGPU => {
-                Ok(Box::new(crate::converters::wgpu_converter::WgpuBatchConverter::new(true, None, None).await?))
+                Ok(Box::new(crate::converters::wgpu_converter::WgpuBatchConverter::new(true, None, None, verbose).await?))
             }
             ConversionMode::Yuvutils => {
                 Ok(Box::new(crate::converters::yuvutils_converter::YuvutilsConverter::new()))
             }
+            #[cfg(feature = "libyuv-mode")]
+            ConversionMode::Libyuv => {
+                Ok(Box::new(crate::converters::libyuv_converter::LibyuvConverter::new()))
+            }
+            #[cfg(not(feature = "libyuv-mode"))]
+            ConversionMode::Libyuv => {
+                anyhow::bail!("Libyuv mode not enabled. Please compile with --features libyuv-mode")
+            }
         }
     }
 
     pub fn available_modes() -> Vec<ConversionMode> {
-        vec![
+        let modes = vec![
             ConversionMode::FFmpeg,
             ConversionMode::OpenCV,
             ConversionMode::Manual,
             ConversionMode::WGPU,
             ConversionMode::Yuvutils,
-        ]
+        ];
+
+        #[cfg(feature = "libyuv-mode")]
+        let modes = {
+            let mut modes = modes;
+            modes.push(ConversionMode::Libyuv);
+            modes
+        };
+
+        modes
     }
 }
 
 pub mod ffmpeg_converter;
 pub mod opencv_converter;
 pub mod manual_converter;
+pub mod gpu_backend;
 pub mod wgpu_converter;
-pub mod yuvutils_converter; 
\ No newline at end of file
+pub mod yuvutils_converter;
+pub mod libyuv_converter; 
\ No newline at end of file
@@ -1,63 +1,201 @@
 use anyhow::Result;
-use crate::converters::{YuvToRgbConverter, FrameData};
+use crate::converters::{build_worker_pool, convert_yuv_to_rgb_software, pack_channels, resize_rgb, ColorMatrix, ColorRange, OutputFormat, ScaleFilter, ScaleTarget, YuvToRgbConverter, FrameData};
+use ffmpeg_next::util::format::Pixel;
 
 /// OpenCV库转换器
-/// 
+///
 /// 使用OpenCV库的cvtColor函数进行YUV到RGB转换
-/// 利用OpenCV优化的色彩空间转换算法
-pub struct OpencvConverter;
+/// 利用OpenCV优化的色彩空间转换算法。OpenCV的内置`cvtColor`代码写死了BT.601系数，换不了
+/// 矩阵：I420/NV12/NV21按limited range换算，4:2:2/4:4:4走的`YCrCb2RGB`按full range换算。
+/// 所以只有帧的`color_space`正好是BT.601、且量化范围跟对应代码一致时才走这条快速路径；
+/// 否则退回到`convert_yuv_to_rgb_software`的逐像素实现，
+/// 保证色彩始终正确而不是"凑合用cvtColor"。
+///
+/// `worker_threads`只影响这条软件回退路径——`cvtColor`本身走OpenCV自己的`parallel_for_`
+/// 后端，已经是多线程的，再在外面套一层行拆分只会增加开销，不会更快。
+pub struct OpencvConverter {
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
+    pool: rayon::ThreadPool,
+}
 
 impl OpencvConverter {
     pub fn new() -> Self {
-        Self
+        Self {
+            scale_target: None,
+            output_format: OutputFormat::default(),
+            pool: build_worker_pool(1).expect("failed to build default rayon thread pool"),
+        }
+    }
+}
+
+impl Default for OpencvConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn inter_flag(filter: ScaleFilter) -> i32 {
+    match filter {
+        ScaleFilter::Point => opencv::imgproc::INTER_NEAREST,
+        ScaleFilter::Bilinear => opencv::imgproc::INTER_LINEAR,
+        ScaleFilter::Bicubic => opencv::imgproc::INTER_CUBIC,
     }
 }
 
 #[async_trait::async_trait(?Send)]
 impl YuvToRgbConverter for OpencvConverter {
     async fn convert(&mut self, frame_data: &FrameData) -> Result<Vec<u8>> {
-        if frame_data.format != ffmpeg_next::util::format::Pixel::YUV420P {
-            anyhow::bail!("OpenCV converter only supports YUV420P format");
+        // YCrCb2RGB（4:2:2/4:4:4用）是full range公式，其余cvtColor代码是limited range
+        let fast_path = match (frame_data.format, frame_data.color_space.matrix, frame_data.color_space.range) {
+            (Pixel::YUV422P | Pixel::YUV444P, ColorMatrix::Bt601, ColorRange::Full) => true,
+            (Pixel::YUV422P | Pixel::YUV444P, _, _) => false,
+            (_, ColorMatrix::Bt601, ColorRange::Limited) => true,
+            _ => false,
+        };
+        if !fast_path {
+            let rgb_data = convert_yuv_to_rgb_software(frame_data, &self.pool)?;
+            let rgb_data = match self.scale_target {
+                Some(target) => resize_rgb(&rgb_data, frame_data.width, frame_data.height, target.width, target.height, target.filter, 3),
+                None => rgb_data,
+            };
+            return Ok(pack_channels(&rgb_data, self.output_format));
         }
-        
+
+        use opencv::core::*;
+        use opencv::imgproc::*;
+
         let width = frame_data.width as i32;
         let height = frame_data.height as i32;
         let y_size = (width * height) as usize;
-        let uv_size = y_size / 4;
-        
-        if frame_data.data.len() < y_size + 2 * uv_size {
-            anyhow::bail!("Invalid YUV data size: expected {}, got {}", 
-                         y_size + 2 * uv_size, frame_data.data.len());
-        }
-        
-        // 使用真正的OpenCV cvt_color进行YUV420P到RGB转换
-        use opencv::core::*;
-        use opencv::imgproc::*;
-        
-        // 创建连续的YUV数据Mat
-        let yuv_data = Mat::from_slice(&frame_data.data)?;
-        let yuv_mat = yuv_data.reshape(1, height * 3 / 2)?;
-        
-        // 使用OpenCV的COLOR_YUV2RGB_I420转换
-        let mut rgb_mat = Mat::default();
-        cvt_color(
-            &yuv_mat,
-            &mut rgb_mat,
-            COLOR_YUV2RGB_I420,
-            0,
-            opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
-        )?;
-        
-        // 提取RGB数据
         let rgb_size = (width * height * 3) as usize;
+
+        let rgb_mat = match frame_data.format {
+            Pixel::YUV420P => {
+                let uv_size = y_size / 4;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV420P data size: expected {}, got {}",
+                                 y_size + 2 * uv_size, frame_data.data.len());
+                }
+
+                // I420把Y、U、V三个平面首尾相接放进同一块height*3/2行的buffer
+                let yuv_data = Mat::from_slice(&frame_data.data)?;
+                let yuv_mat = yuv_data.reshape(1, height * 3 / 2)?;
+
+                let mut rgb_mat = Mat::default();
+                cvt_color(&yuv_mat, &mut rgb_mat, COLOR_YUV2RGB_I420, 0, AlgorithmHint::ALGO_HINT_DEFAULT)?;
+                rgb_mat
+            }
+            Pixel::NV12 | Pixel::NV21 => {
+                let uv_size = width as usize / 2 * height as usize / 2 * 2;
+                if frame_data.data.len() < y_size + uv_size {
+                    anyhow::bail!("Invalid NV12/NV21 data size: expected {}, got {}",
+                                 y_size + uv_size, frame_data.data.len());
+                }
+
+                // NV12/NV21同样是Y平面后面接一个height/2行的交织UV/VU平面
+                let yuv_data = Mat::from_slice(&frame_data.data)?;
+                let yuv_mat = yuv_data.reshape(1, height * 3 / 2)?;
+                let code = if frame_data.format == Pixel::NV12 { COLOR_YUV2RGB_NV12 } else { COLOR_YUV2RGB_NV21 };
+
+                let mut rgb_mat = Mat::default();
+                cvt_color(&yuv_mat, &mut rgb_mat, code, 0, AlgorithmHint::ALGO_HINT_DEFAULT)?;
+                rgb_mat
+            }
+            Pixel::YUV422P => {
+                // OpenCV没有现成的平面4:2:2 cvtColor代码，手工把U/V平面线性放大到全分辨率，
+                // 合并成YCrCb三通道Mat后再走通用的COLOR_YCrCb2RGB。
+                let uv_size = width as usize / 2 * height as usize;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV422P data size: expected {}, got {}",
+                                 y_size + 2 * uv_size, frame_data.data.len());
+                }
+
+                let y_mat = Mat::from_slice(&frame_data.data[0..y_size])?.reshape(1, height)?;
+                let u_mat = Mat::from_slice(&frame_data.data[y_size..y_size + uv_size])?.reshape(1, height)?;
+                let v_mat = Mat::from_slice(&frame_data.data[y_size + uv_size..y_size + 2 * uv_size])?.reshape(1, height)?;
+
+                let mut u_full = Mat::default();
+                let mut v_full = Mat::default();
+                let full_size = Size::new(width, height);
+                resize(&u_mat, &mut u_full, full_size, 0.0, 0.0, INTER_LINEAR)?;
+                resize(&v_mat, &mut v_full, full_size, 0.0, 0.0, INTER_LINEAR)?;
+
+                let mut planes = Vector::<Mat>::new();
+                planes.push(y_mat);
+                planes.push(v_full);
+                planes.push(u_full);
+                let mut ycrcb = Mat::default();
+                merge(&planes, &mut ycrcb)?;
+
+                let mut rgb_mat = Mat::default();
+                cvt_color(&ycrcb, &mut rgb_mat, COLOR_YCrCb2RGB, 0, AlgorithmHint::ALGO_HINT_DEFAULT)?;
+                rgb_mat
+            }
+            Pixel::YUV444P => {
+                // 平面4:4:4：三个平面本来就同宽同高，不需要像4:2:2那样先把色度放大，
+                // 直接合并成YCrCb三通道Mat再走COLOR_YCrCb2RGB。
+                let uv_size = y_size;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV444P data size: expected {}, got {}",
+                                 y_size + 2 * uv_size, frame_data.data.len());
+                }
+
+                let y_mat = Mat::from_slice(&frame_data.data[0..y_size])?.reshape(1, height)?;
+                let u_mat = Mat::from_slice(&frame_data.data[y_size..y_size + uv_size])?.reshape(1, height)?;
+                let v_mat = Mat::from_slice(&frame_data.data[y_size + uv_size..y_size + 2 * uv_size])?.reshape(1, height)?;
+
+                let mut planes = Vector::<Mat>::new();
+                planes.push(y_mat);
+                planes.push(v_mat);
+                planes.push(u_mat);
+                let mut ycrcb = Mat::default();
+                merge(&planes, &mut ycrcb)?;
+
+                let mut rgb_mat = Mat::default();
+                cvt_color(&ycrcb, &mut rgb_mat, COLOR_YCrCb2RGB, 0, AlgorithmHint::ALGO_HINT_DEFAULT)?;
+                rgb_mat
+            }
+            other => anyhow::bail!("OpenCV converter does not support {:?} format", other),
+        };
+
+        let (rgb_mat, rgb_size) = match self.scale_target {
+            Some(target) => {
+                let mut scaled = Mat::default();
+                resize(
+                    &rgb_mat,
+                    &mut scaled,
+                    Size::new(target.width as i32, target.height as i32),
+                    0.0,
+                    0.0,
+                    inter_flag(target.filter),
+                )?;
+                (scaled, (target.width * target.height * 3) as usize)
+            }
+            None => (rgb_mat, rgb_size),
+        };
+
         let rgb_data = rgb_mat.data_bytes()?.to_vec();
-        
-        if rgb_data.len() >= rgb_size {
-            Ok(rgb_data[0..rgb_size].to_vec())
-        } else {
-            anyhow::bail!("OpenCV conversion resulted in insufficient data: expected {}, got {}", 
+
+        if rgb_data.len() < rgb_size {
+            anyhow::bail!("OpenCV conversion resulted in insufficient data: expected {}, got {}",
                          rgb_size, rgb_data.len());
         }
+
+        Ok(pack_channels(&rgb_data[0..rgb_size], self.output_format))
+    }
+
+    fn set_scale_target(&mut self, target: Option<ScaleTarget>) {
+        self.scale_target = target;
     }
 
-} 
\ No newline at end of file
+    fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
+    }
+
+    fn set_worker_threads(&mut self, threads: usize) {
+        if let Ok(pool) = build_worker_pool(threads) {
+            self.pool = pool;
+        }
+    }
+}
\ No newline at end of file
@@ -1,15 +1,53 @@
 use anyhow::Result;
-use crate::converter::{YuvToRgbConverter, FrameData, ConversionMode};
+use rayon::prelude::*;
+use crate::converters::{build_worker_pool, chunk_rows, pack_channels, resize_rgb, ColorMatrix, ColorRange, ColorSpace, OutputFormat, ScaleTarget, YuvToRgbConverter, FrameData};
+use ffmpeg_next::util::format::Pixel;
 
 /// YuvUtils-rs高性能转换器
-/// 
-/// 使用yuvutils-rs库进行SIMD优化的YUV到RGB转换
-/// 专门针对YUV420P格式优化，提供纯Rust的高性能实现
-pub struct YuvutilsConverter;
+///
+/// 使用yuvutils-rs库进行SIMD优化的YUV到RGB转换，支持YUV420P/YUV422P平面格式
+/// 和NV12/NV21半平面格式，提供纯Rust的高性能实现。RGBA/BGRA走库自带的
+/// `*_to_rgba`/`*_to_bgra`入口，跟RGB一样是单次SIMD调用；BGR没有对应的原生入口，
+/// 借用RGB结果再交换R/B通道。
+///
+/// 单帧内部按`worker_threads`把输出行切成不相交的区块，分给rayon线程池并行跑各自的
+/// SIMD调用——每块只是一个行数更少的子图，用同一套`yuv420_to_rgb`等入口处理，不需要
+/// 重新实现kernel本身。线程池在`set_worker_threads`时构建一次，跨帧复用，不是每帧
+/// 现造一个。
+pub struct YuvutilsConverter {
+    scale_target: Option<ScaleTarget>,
+    output_format: OutputFormat,
+    pool: rayon::ThreadPool,
+}
 
 impl YuvutilsConverter {
     pub fn new() -> Self {
-        Self
+        Self {
+            scale_target: None,
+            output_format: OutputFormat::default(),
+            pool: build_worker_pool(1).expect("failed to build default rayon thread pool"),
+        }
+    }
+}
+
+impl Default for YuvutilsConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn yuv_range(range: ColorRange) -> yuvutils_rs::YuvRange {
+    match range {
+        ColorRange::Limited => yuvutils_rs::YuvRange::Limited,
+        ColorRange::Full => yuvutils_rs::YuvRange::Full,
+    }
+}
+
+fn yuv_matrix(matrix: ColorMatrix) -> yuvutils_rs::YuvStandardMatrix {
+    match matrix {
+        ColorMatrix::Bt601 => yuvutils_rs::YuvStandardMatrix::Bt601,
+        ColorMatrix::Bt709 => yuvutils_rs::YuvStandardMatrix::Bt709,
+        ColorMatrix::Bt2020 => yuvutils_rs::YuvStandardMatrix::Bt2020,
     }
 }
 
@@ -17,49 +55,186 @@ impl YuvutilsConverter {
 impl YuvToRgbConverter for YuvutilsConverter {
     async fn convert(&mut self, frame_data: &FrameData) -> Result<Vec<u8>> {
         use yuvutils_rs::*;
-        
-        if frame_data.format != ffmpeg_next::util::format::Pixel::YUV420P {
-            anyhow::bail!("Yuvutils converter only supports YUV420P format");
-        }
-        
+
         let width = frame_data.width;
         let height = frame_data.height;
         let y_size = (width * height) as usize;
-        let uv_size = y_size / 4;
-        
-        if frame_data.data.len() < y_size + 2 * uv_size {
-            anyhow::bail!("Invalid YUV data size");
+        let ColorSpace { matrix, range } = frame_data.color_space;
+        let range = yuv_range(range);
+        let matrix = yuv_matrix(matrix);
+        let output_format = self.output_format;
+        let pool = &self.pool;
+        let threads = pool.current_num_threads();
+
+        // yuvutils-rs有原生的RGBA/BGRA转换入口，跟RGB一样是单次SIMD调用，不需要
+        // 额外的`pack_channels`重排；Bgr没有对应的原生入口，借用RGB的结果再换B/R。
+        let channels = output_format.channels();
+        let mut pixel_data = vec![0u8; (width * height) as usize * channels];
+        let stride = width * channels as u32;
+
+        match frame_data.format {
+            Pixel::YUV420P => {
+                let uv_size = y_size / 4;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV420P data size");
+                }
+
+                let y_plane = &frame_data.data[0..y_size];
+                let u_plane = &frame_data.data[y_size..y_size + uv_size];
+                let v_plane = &frame_data.data[y_size + uv_size..y_size + 2 * uv_size];
+                let (y_stride, uv_stride) = (width, width / 2);
+
+                let rows = chunk_rows(height, threads, 2);
+                let chunk_bytes = (stride as usize) * rows as usize;
+
+                pool.install(|| -> Result<()> {
+                    pixel_data.par_chunks_mut(chunk_bytes.max(stride as usize)).enumerate().try_for_each(|(chunk_idx, chunk)| -> Result<()> {
+                        let row_start = chunk_idx as u32 * rows;
+                        let rows_in_chunk = (chunk.len() / stride as usize) as u32;
+                        if rows_in_chunk == 0 {
+                            return Ok(());
+                        }
+                        let chroma_row_start = row_start / 2;
+                        let chroma_rows = rows_in_chunk.div_ceil(2);
+
+                        let yuv_image = YuvPlanarImage {
+                            y_plane: &y_plane[(row_start * y_stride) as usize..((row_start + rows_in_chunk) * y_stride) as usize],
+                            u_plane: &u_plane[(chroma_row_start * uv_stride) as usize..((chroma_row_start + chroma_rows) * uv_stride) as usize],
+                            v_plane: &v_plane[(chroma_row_start * uv_stride) as usize..((chroma_row_start + chroma_rows) * uv_stride) as usize],
+                            width,
+                            height: rows_in_chunk,
+                            y_stride,
+                            u_stride: uv_stride,
+                            v_stride: uv_stride,
+                        };
+
+                        match output_format {
+                            OutputFormat::Rgb | OutputFormat::Bgr => yuv420_to_rgb(&yuv_image, chunk, stride, range, matrix)?,
+                            OutputFormat::Rgba => yuv420_to_rgba(&yuv_image, chunk, stride, range, matrix)?,
+                            OutputFormat::Bgra => yuv420_to_bgra(&yuv_image, chunk, stride, range, matrix)?,
+                        }
+                        Ok(())
+                    })
+                })?;
+            }
+            Pixel::YUV422P => {
+                let uv_size = (width / 2 * height) as usize;
+                if frame_data.data.len() < y_size + 2 * uv_size {
+                    anyhow::bail!("Invalid YUV422P data size");
+                }
+
+                let y_plane = &frame_data.data[0..y_size];
+                let u_plane = &frame_data.data[y_size..y_size + uv_size];
+                let v_plane = &frame_data.data[y_size + uv_size..y_size + 2 * uv_size];
+                let (y_stride, uv_stride) = (width, width / 2);
+
+                // 4:2:2色度和亮度同高，区块不需要按2对齐。
+                let rows = chunk_rows(height, threads, 1);
+                let chunk_bytes = (stride as usize) * rows as usize;
+
+                pool.install(|| -> Result<()> {
+                    pixel_data.par_chunks_mut(chunk_bytes.max(stride as usize)).enumerate().try_for_each(|(chunk_idx, chunk)| -> Result<()> {
+                        let row_start = chunk_idx as u32 * rows;
+                        let rows_in_chunk = (chunk.len() / stride as usize) as u32;
+                        if rows_in_chunk == 0 {
+                            return Ok(());
+                        }
+
+                        let yuv_image = YuvPlanarImage {
+                            y_plane: &y_plane[(row_start * y_stride) as usize..((row_start + rows_in_chunk) * y_stride) as usize],
+                            u_plane: &u_plane[(row_start * uv_stride) as usize..((row_start + rows_in_chunk) * uv_stride) as usize],
+                            v_plane: &v_plane[(row_start * uv_stride) as usize..((row_start + rows_in_chunk) * uv_stride) as usize],
+                            width,
+                            height: rows_in_chunk,
+                            y_stride,
+                            u_stride: uv_stride,
+                            v_stride: uv_stride,
+                        };
+
+                        match output_format {
+                            OutputFormat::Rgb | OutputFormat::Bgr => yuv422_to_rgb(&yuv_image, chunk, stride, range, matrix)?,
+                            OutputFormat::Rgba => yuv422_to_rgba(&yuv_image, chunk, stride, range, matrix)?,
+                            OutputFormat::Bgra => yuv422_to_bgra(&yuv_image, chunk, stride, range, matrix)?,
+                        }
+                        Ok(())
+                    })
+                })?;
+            }
+            Pixel::NV12 | Pixel::NV21 => {
+                let uv_size = (width / 2 * height / 2 * 2) as usize;
+                if frame_data.data.len() < y_size + uv_size {
+                    anyhow::bail!("Invalid NV12/NV21 data size");
+                }
+
+                let y_plane = &frame_data.data[0..y_size];
+                let uv_plane = &frame_data.data[y_size..y_size + uv_size];
+                let (y_stride, uv_stride) = (width, width);
+                let is_nv12 = frame_data.format == Pixel::NV12;
+
+                let rows = chunk_rows(height, threads, 2);
+                let chunk_bytes = (stride as usize) * rows as usize;
+
+                pool.install(|| -> Result<()> {
+                    pixel_data.par_chunks_mut(chunk_bytes.max(stride as usize)).enumerate().try_for_each(|(chunk_idx, chunk)| -> Result<()> {
+                        let row_start = chunk_idx as u32 * rows;
+                        let rows_in_chunk = (chunk.len() / stride as usize) as u32;
+                        if rows_in_chunk == 0 {
+                            return Ok(());
+                        }
+                        let chroma_row_start = row_start / 2;
+                        let chroma_rows = rows_in_chunk.div_ceil(2);
+
+                        let yuv_image = YuvBiPlanarImage {
+                            y_plane: &y_plane[(row_start * y_stride) as usize..((row_start + rows_in_chunk) * y_stride) as usize],
+                            uv_plane: &uv_plane[(chroma_row_start * uv_stride) as usize..((chroma_row_start + chroma_rows) * uv_stride) as usize],
+                            width,
+                            height: rows_in_chunk,
+                            y_stride,
+                            uv_stride,
+                        };
+
+                        if is_nv12 {
+                            match output_format {
+                                OutputFormat::Rgb | OutputFormat::Bgr => nv12_to_rgb(&yuv_image, chunk, stride, range, matrix)?,
+                                OutputFormat::Rgba => nv12_to_rgba(&yuv_image, chunk, stride, range, matrix)?,
+                                OutputFormat::Bgra => nv12_to_bgra(&yuv_image, chunk, stride, range, matrix)?,
+                            }
+                        } else {
+                            match output_format {
+                                OutputFormat::Rgb | OutputFormat::Bgr => nv21_to_rgb(&yuv_image, chunk, stride, range, matrix)?,
+                                OutputFormat::Rgba => nv21_to_rgba(&yuv_image, chunk, stride, range, matrix)?,
+                                OutputFormat::Bgra => nv21_to_bgra(&yuv_image, chunk, stride, range, matrix)?,
+                            }
+                        }
+                        Ok(())
+                    })
+                })?;
+            }
+            other => anyhow::bail!("Yuvutils converter does not support {:?} format", other),
         }
-        
-        let y_plane = &frame_data.data[0..y_size];
-        let u_plane = &frame_data.data[y_size..y_size + uv_size];
-        let v_plane = &frame_data.data[y_size + uv_size..y_size + 2 * uv_size];
-        
-        let mut rgb_data = vec![0u8; (width * height * 3) as usize];
-        
-        let yuv_image = YuvPlanarImage {
-            y_plane,
-            u_plane,
-            v_plane,
-            width,
-            height,
-            y_stride: width,
-            u_stride: width / 2,
-            v_stride: width / 2,
+
+        let pixel_data = match self.scale_target {
+            Some(target) => resize_rgb(&pixel_data, width, height, target.width, target.height, target.filter, channels),
+            None => pixel_data,
         };
-        
-        yuv420_to_rgb(
-            &yuv_image,
-            &mut rgb_data,
-            width * 3,
-            YuvRange::Limited,
-            YuvStandardMatrix::Bt709,
-        )?;
-        
-        Ok(rgb_data)
+
+        Ok(match output_format {
+            OutputFormat::Bgr => pack_channels(&pixel_data, OutputFormat::Bgr),
+            _ => pixel_data,
+        })
+    }
+
+    fn set_scale_target(&mut self, target: Option<ScaleTarget>) {
+        self.scale_target = target;
     }
 
-    fn get_mode(&self) -> ConversionMode {
-        ConversionMode::Yuvutils
+    fn set_output_format(&mut self, format: OutputFormat) {
+        self.output_format = format;
     }
-} 
\ No newline at end of file
+
+    fn set_worker_threads(&mut self, threads: usize) {
+        if let Ok(pool) = build_worker_pool(threads) {
+            self.pool = pool;
+        }
+    }
+}
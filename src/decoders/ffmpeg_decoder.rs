@@ -0,0 +1,224 @@
+use anyhow::Result;
+use ffmpeg_next::util::format::Pixel;
+use std::path::Path;
+use super::{Decoder, FrameData, FrameDataPool, ProcessingResult};
+
+/// 用FFmpeg自带解码器跑的解码器：demux+软解完全交给`ffmpeg-next`，跟`OpenCVDecoder`
+/// 一样按`duration`/`avg_frame_rate`算出目标输出帧数/采样间隔，只是这里没有seek，
+/// 纯顺序解码+按时间戳节流。解码出来的平面按`stride`逐行拷贝进`FrameData`，支持
+/// YUV420P/NV12/NV21/YUV422P/YUV444P，其余像素格式直接报错。
+pub struct FFmpegDecoder {
+    pool: FrameDataPool,
+}
+
+impl FFmpegDecoder {
+    pub fn new() -> Self {
+        let estimated_frame_size = (3840 * 2160 * 3 / 2) as usize; // 假设最大4K分辨率
+        Self {
+            pool: FrameDataPool::new(16, estimated_frame_size),
+        }
+    }
+}
+
+impl Decoder for FFmpegDecoder {
+    fn extract_frames_streaming(
+        &mut self,
+        input_path: &str,
+        max_frames: u32,
+        sample_fps: u32,
+    ) -> Result<(ProcessingResult, Vec<FrameData>)> {
+        let mut input = ffmpeg_next::format::input(&Path::new(input_path))?;
+
+        let video_stream_index = input.streams()
+            .enumerate()
+            .find(|(_, stream)| stream.parameters().medium() == ffmpeg_next::media::Type::Video)
+            .map(|(i, _)| i)
+            .ok_or_else(|| anyhow::anyhow!("未找到视频流"))?;
+
+        let stream = input.streams().nth(video_stream_index).unwrap();
+        let time_base = stream.time_base();
+        let duration = stream.duration();
+        let frame_rate = stream.avg_frame_rate();
+
+        // 跟`OpenCVDecoder`一样：时长/帧率不可信时（常见于部分网络流/容器）放弃按目标
+        // 总帧数换算，退化成纯顺序解码，靠每帧自己的时间戳判断该不该采样。
+        let video_duration_seconds = if duration > 0 {
+            duration as f64 * f64::from(time_base)
+        } else {
+            0.0
+        };
+        let total_video_frames = if frame_rate.numerator() > 0 && frame_rate.denominator() > 0 && video_duration_seconds > 0.0 {
+            let fps = frame_rate.numerator() as f64 / frame_rate.denominator() as f64;
+            (video_duration_seconds * fps) as u32
+        } else {
+            0
+        };
+
+        let final_output_frames = if max_frames == 0 {
+            if sample_fps > 0 && video_duration_seconds > 0.0 {
+                (video_duration_seconds * sample_fps as f64) as u32
+            } else if total_video_frames > 0 {
+                total_video_frames
+            } else {
+                u32::MAX // 总帧数也不可信：交给解码自然结束（读到流尾即停）
+            }
+        } else {
+            max_frames
+        };
+
+        let frame_interval = if sample_fps > 0 {
+            1.0 / sample_fps as f64
+        } else if max_frames > 0 && video_duration_seconds > 0.0 {
+            video_duration_seconds / max_frames as f64
+        } else {
+            0.0
+        };
+
+        println!("FFmpeg解码器信息: 时长={:.2}秒, 总帧数={}, 目标输出帧数={}, 帧间隔={:.4}秒",
+                 video_duration_seconds, total_video_frames, final_output_frames, frame_interval);
+
+        let decoder_context = ffmpeg_next::codec::context::Context::from_parameters(stream.parameters())?;
+        let mut decoder = decoder_context.decoder().video()?;
+
+        let mut frames = Vec::new();
+        let mut frame_count = 0u32;
+        let mut next_extract_time = 0.0;
+        let start_time = std::time::Instant::now();
+
+        let mut decoded = ffmpeg_next::util::frame::video::Video::empty();
+        'demux: for (packet_stream, packet) in input.packets() {
+            if frame_count >= final_output_frames {
+                break;
+            }
+            if packet_stream.index() != video_stream_index {
+                continue;
+            }
+
+            decoder.send_packet(&packet)?;
+            while decoder.receive_frame(&mut decoded).is_ok() {
+                if frame_count >= final_output_frames {
+                    break 'demux;
+                }
+
+                let timestamp = decoded.timestamp().unwrap_or(0) as f64 * f64::from(time_base);
+
+                let should_extract = if frame_interval == 0.0 {
+                    true
+                } else {
+                    timestamp >= next_extract_time
+                };
+
+                if !should_extract {
+                    continue;
+                }
+
+                if frame_interval > 0.0 {
+                    next_extract_time += frame_interval;
+                }
+
+                frame_count += 1;
+                let width = decoded.width();
+                let height = decoded.height();
+                let format = decoded.format();
+                let yuv_data = extract_yuv_planes(&mut self.pool, &decoded, width, height)?;
+
+                frames.push(FrameData {
+                    frame_number: frame_count,
+                    width,
+                    height,
+                    yuv_data,
+                    format,
+                    sei: None,
+                    color_space: crate::converters::ColorSpace::default(),
+                });
+            }
+        }
+
+        let total_duration = start_time.elapsed();
+        Ok((
+            ProcessingResult {
+                frames_processed: frame_count,
+                total_duration,
+            },
+            frames,
+        ))
+    }
+}
+
+/// 把解码出来的帧按`stride`逐行拷贝进一份紧凑的缓冲区，支持YUV420P/NV12/NV21/
+/// YUV422P/YUV444P；没有行padding（`stride == width`）时退化成一次性整块拷贝。
+/// 其余像素格式直接报错，交给上层决定要不要换转换模式，不做语义不对的兜底拷贝。
+fn extract_yuv_planes(
+    pool: &mut FrameDataPool,
+    decoded: &ffmpeg_next::util::frame::video::Video,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let y_size = width * height;
+
+    match decoded.format() {
+        Pixel::YUV420P => {
+            let uv_size = y_size / 4;
+            let total_size = y_size + 2 * uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.clear();
+
+            let uv_width = width / 2;
+            let uv_height = height / 2;
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, uv_width, uv_height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, uv_width, uv_height);
+
+            Ok(frame_data)
+        }
+        Pixel::NV12 | Pixel::NV21 => {
+            // 半平面4:2:0：Y平面之后紧跟一个高度减半、宽度不变的交织UV（或VU）平面
+            let uv_size = width * (height / 2);
+            let total_size = y_size + uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.clear();
+
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, width, height / 2);
+
+            Ok(frame_data)
+        }
+        Pixel::YUV422P => {
+            let uv_size = (width / 2) * height;
+            let total_size = y_size + 2 * uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.clear();
+
+            let uv_width = width / 2;
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, uv_width, height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, uv_width, height);
+
+            Ok(frame_data)
+        }
+        Pixel::YUV444P => {
+            let total_size = y_size * 3;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.clear();
+
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, width, height);
+
+            Ok(frame_data)
+        }
+        other => anyhow::bail!("FFmpeg解码器暂不支持 {:?} 像素格式", other),
+    }
+}
+
+fn copy_plane(frame_data: &mut Vec<u8>, plane: &[u8], stride: usize, width: usize, height: usize) {
+    if stride == width {
+        frame_data.extend_from_slice(&plane[..width * height]);
+    } else {
+        for row in 0..height {
+            let start = row * stride;
+            frame_data.extend_from_slice(&plane[start..start + width]);
+        }
+    }
+}
@@ -8,6 +8,12 @@ pub enum DecoderType {
     /// 使用OpenCV解码器
     #[value(name = "opencv")]
     OpenCV,
+    /// 裸H.264 Annex B码流解码器（无容器）
+    #[value(name = "annexb")]
+    AnnexB,
+    /// RTSP/RTP网络视频流解码器
+    #[value(name = "rtsp")]
+    Rtsp,
 }
 
 impl DecoderType {
@@ -15,6 +21,8 @@ impl DecoderType {
         match self {
             DecoderType::FFmpeg => "ffmpeg",
             DecoderType::OpenCV => "opencv",
+            DecoderType::AnnexB => "annexb",
+            DecoderType::Rtsp => "rtsp",
         }
     }
 
@@ -22,6 +30,8 @@ impl DecoderType {
         match self {
             DecoderType::FFmpeg => "使用FFmpeg库进行视频解码",
             DecoderType::OpenCV => "使用OpenCV库进行视频解码",
+            DecoderType::AnnexB => "直接扫描Annex B起始码解码裸H.264码流",
+            DecoderType::Rtsp => "通过RTSP/RTP拉取实时H.264视频流",
         }
     }
 }
@@ -39,6 +49,11 @@ pub struct FrameData {
     pub height: u32,
     pub yuv_data: Vec<u8>,
     pub format: ffmpeg_next::util::format::Pixel,
+    /// 从最近一个SEI NAL解析出的时间码/用户数据，不是每帧都有（取决于流是否携带SEI）。
+    pub sei: Option<crate::decoders::mp4_decoder::SeiMetadata>,
+    /// 从SPS VUI（`matrix_coefficients`/`video_full_range_flag`）推断出的颜色空间。VUI里没有
+    /// 矩阵信息时按分辨率猜（HD及以上BT.709，否则BT.601）；完全没有SPS信息时回退到BT.709 limited。
+    pub color_space: crate::converters::ColorSpace,
 }
 
 impl From<FrameData> for crate::converters::ChannelFrameData {
@@ -49,6 +64,7 @@ impl From<FrameData> for crate::converters::ChannelFrameData {
             height: frame_data.height,
             yuv_data: frame_data.yuv_data,
             format: frame_data.format,
+            color_space: frame_data.color_space,
         }
     }
 }
@@ -61,6 +77,8 @@ impl From<crate::converters::ChannelFrameData> for FrameData {
             height: channel_frame_data.height,
             yuv_data: channel_frame_data.yuv_data,
             format: channel_frame_data.format,
+            sei: None,
+            color_space: channel_frame_data.color_space,
         }
     }
 }
@@ -114,6 +132,8 @@ impl DecoderFactory {
         match decoder_type {
             DecoderType::FFmpeg => Ok(Box::new(ffmpeg_decoder::FFmpegDecoder::new())),
             DecoderType::OpenCV => Ok(Box::new(opencv_decoder::OpenCVDecoder::new())),
+            DecoderType::AnnexB => Ok(Box::new(mp4_decoder::AnnexBDecoder::new())),
+            DecoderType::Rtsp => Ok(Box::new(rtsp_decoder::RtspDecoder::new())),
         }
     }
 
@@ -121,6 +141,8 @@ impl DecoderFactory {
         vec![
             DecoderType::FFmpeg,
             DecoderType::OpenCV,
+            DecoderType::AnnexB,
+            DecoderType::Rtsp,
         ]
     }
 }
@@ -153,4 +175,6 @@ pub async fn extract_frames_with_decoder(
 }
 
 pub mod ffmpeg_decoder;
-pub mod opencv_decoder; 
\ No newline at end of file
+pub mod opencv_decoder;
+pub mod mp4_decoder;
+pub mod rtsp_decoder;
\ No newline at end of file
@@ -116,6 +116,22 @@ impl<'a> NalUnit<'a> {
     }
 }
 
+/// 轻量扫一遍MP4样本（AVCC长度前缀格式），判断其中是否包含`IdrSlice`NAL。
+/// 用来在GOP并行解码前切分出自给自足的GOP边界，不做任何解码或转换工作。
+fn sample_contains_idr(sample: &[u8], length_size: u8) -> bool {
+    let mut stream = sample;
+    while !stream.is_empty() {
+        let Some((unit, remaining)) = NalUnit::from_stream(stream, length_size) else {
+            break;
+        };
+        if unit.nal_type == NalType::IdrSlice {
+            return true;
+        }
+        stream = remaining;
+    }
+    false
+}
+
 /// Converter from NAL units from the MP4 to the Annex B format expected by openh264.
 ///
 /// It also inserts SPS and PPS units from the MP4 header into the stream.
@@ -214,6 +230,488 @@ impl Mp4BitstreamConverter {
     }
 }
 
+/// 去除NAL payload中的emulation-prevention字节（`00 00 03` -> `00 00`），
+/// 这样后续的Exp-Golomb读取才不会把防竞争字节当成真实语法元素。
+fn strip_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zero_run = 0;
+    for &b in data {
+        if zero_run >= 2 && b == 0x03 {
+            zero_run = 0;
+            continue;
+        }
+        out.push(b);
+        zero_run = if b == 0 { zero_run + 1 } else { 0 };
+    }
+    out
+}
+
+/// 读取SPS RBSP用的比特读取器，支持定长位读取和Exp-Golomb(ue/se)编码。
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit_pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, bit_pos: 0 }
+    }
+
+    fn read_bit(&mut self) -> Option<u32> {
+        let byte_idx = self.bit_pos / 8;
+        if byte_idx >= self.data.len() {
+            return None;
+        }
+        let bit_idx = 7 - (self.bit_pos % 8);
+        self.bit_pos += 1;
+        Some(((self.data[byte_idx] >> bit_idx) & 1) as u32)
+    }
+
+    fn read_bits(&mut self, n: u32) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | self.read_bit()?;
+        }
+        Some(value)
+    }
+
+    /// 无符号Exp-Golomb编码（ue(v)）
+    fn read_ue(&mut self) -> Option<u32> {
+        let mut leading_zero_bits = 0u32;
+        while self.read_bit()? == 0 {
+            leading_zero_bits += 1;
+            if leading_zero_bits > 32 {
+                return None;
+            }
+        }
+        if leading_zero_bits == 0 {
+            return Some(0);
+        }
+        let rest = self.read_bits(leading_zero_bits)?;
+        Some((1u32 << leading_zero_bits) - 1 + rest)
+    }
+
+    /// 有符号Exp-Golomb编码（se(v)）
+    fn read_se(&mut self) -> Option<i32> {
+        let code = self.read_ue()?;
+        let value = (code as i64 + 1) / 2;
+        if code % 2 == 0 {
+            Some((-value) as i32)
+        } else {
+            Some(value as i32)
+        }
+    }
+}
+
+/// 从SPS解析出的真实分辨率与帧率，用于替代MP4轨道里可能因为裁剪/VFR而不准确的数值。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpsInfo {
+    pub width: u32,
+    pub height: u32,
+    pub fps: Option<f64>,
+    /// `pic_struct_present_flag`，pic_timing SEI是否带`pic_struct`字段
+    pub pic_struct_present: bool,
+    /// HRD参数里`cpb_removal_delay`/`dpb_output_delay`的比特长度，供pic_timing SEI使用
+    pub cpb_removal_delay_length: Option<u32>,
+    pub dpb_output_delay_length: Option<u32>,
+    /// pic_timing SEI里`time_offset`字段的比特长度
+    pub time_offset_length: Option<u32>,
+    /// VUI `video_signal_type`里的`matrix_coefficients`（ITU-T H.264 Table E-5），
+    /// 决定该用BT.601/BT.709/BT.2020里的哪一套Kr/Kb系数。
+    pub matrix_coefficients: Option<u8>,
+    /// VUI `video_full_range_flag`：true表示0-255满摆幅，false（默认）是16-235/16-240的studio range。
+    pub video_full_range: bool,
+}
+
+/// 把SPS VUI里的`matrix_coefficients`/`video_full_range_flag`映射成`ColorSpace`。
+/// 解析不到VUI颜色信息时，按分辨率猜：HD及以上给BT.709，否则给BT.601（都是limited range）。
+pub fn detect_color_space(sps: &SpsInfo, width: u32, height: u32) -> crate::converters::ColorSpace {
+    use crate::converters::{ColorMatrix, ColorRange, ColorSpace};
+
+    let range = if sps.video_full_range { ColorRange::Full } else { ColorRange::Limited };
+
+    let matrix = match sps.matrix_coefficients {
+        Some(1) => ColorMatrix::Bt709,
+        Some(5) | Some(6) => ColorMatrix::Bt601,
+        Some(9) | Some(10) => ColorMatrix::Bt2020,
+        _ => {
+            // matrix_coefficients缺失或是Unspecified(2)：按分辨率猜，HD及以上多半是BT.709
+            if width >= 1280 || height >= 720 {
+                ColorMatrix::Bt709
+            } else {
+                ColorMatrix::Bt601
+            }
+        }
+    };
+
+    ColorSpace { matrix, range }
+}
+
+/// 解析HRD参数（`hrd_parameters()`语法，H.264附录E），只为拿到pic_timing SEI要用的
+/// `cpb_removal_delay_length_minus1`/`dpb_output_delay_length_minus1`/`time_offset_length`。
+fn parse_hrd_parameters(r: &mut BitReader) -> Option<(u32, u32, u32)> {
+    let cpb_cnt_minus1 = r.read_ue()?;
+    let _bit_rate_scale = r.read_bits(4)?;
+    let _cpb_size_scale = r.read_bits(4)?;
+    for _ in 0..=cpb_cnt_minus1 {
+        let _bit_rate_value_minus1 = r.read_ue()?;
+        let _cpb_size_value_minus1 = r.read_ue()?;
+        let _cbr_flag = r.read_bit()?;
+    }
+    let initial_cpb_removal_delay_length_minus1 = r.read_bits(5)?;
+    let _ = initial_cpb_removal_delay_length_minus1;
+    let cpb_removal_delay_length_minus1 = r.read_bits(5)?;
+    let dpb_output_delay_length_minus1 = r.read_bits(5)?;
+    let time_offset_length = r.read_bits(5)?;
+    Some((cpb_removal_delay_length_minus1 + 1, dpb_output_delay_length_minus1 + 1, time_offset_length))
+}
+
+/// 解析单个SPS NAL（已去掉起始码，保留`nal_header`）得到裁剪后的宽高/帧率。
+/// VUI里的SAR只会被读过去，不保存到`SpsInfo`，宽高也不按SAR换算。
+///
+/// 只解析到我们需要的字段为止；一旦某个可选段（VUI等）缺失就直接返回已算出的部分。
+pub(crate) fn parse_sps(sps_nal: &[u8]) -> Option<SpsInfo> {
+    if sps_nal.len() < 4 {
+        return None;
+    }
+    let rbsp = strip_emulation_prevention(&sps_nal[1..]); // 跳过NAL header字节
+    let mut r = BitReader::new(&rbsp);
+
+    let profile_idc = r.read_bits(8)?;
+    let _constraint_flags_and_reserved = r.read_bits(8)?;
+    let _level_idc = r.read_bits(8)?;
+    let _seq_parameter_set_id = r.read_ue()?;
+
+    let high_profiles = [100, 110, 122, 244, 44, 83, 86, 118, 128];
+    if high_profiles.contains(&profile_idc) {
+        let chroma_format_idc = r.read_ue()?;
+        if chroma_format_idc == 3 {
+            let _separate_colour_plane_flag = r.read_bit()?;
+        }
+        let _bit_depth_luma_minus8 = r.read_ue()?;
+        let _bit_depth_chroma_minus8 = r.read_ue()?;
+        let _qpprime_y_zero_transform_bypass_flag = r.read_bit()?;
+        let seq_scaling_matrix_present_flag = r.read_bit()?;
+        if seq_scaling_matrix_present_flag != 0 {
+            let count = if chroma_format_idc != 3 { 8 } else { 12 };
+            for i in 0..count {
+                let seq_scaling_list_present_flag = r.read_bit()?;
+                if seq_scaling_list_present_flag != 0 {
+                    // 跳过scaling list本体：我们不需要它的值，但必须正确消费比特位。
+                    // 前6个是4x4列表（16个系数），其余是8x8列表（64个系数）。
+                    let size = if i < 6 { 16 } else { 64 };
+                    let mut last_scale = 8i32;
+                    let mut next_scale = 8i32;
+                    for _ in 0..size {
+                        if next_scale != 0 {
+                            let delta_scale = r.read_se()?;
+                            next_scale = (last_scale + delta_scale + 256) % 256;
+                        }
+                        last_scale = if next_scale == 0 { last_scale } else { next_scale };
+                    }
+                }
+            }
+        }
+    }
+
+    let _log2_max_frame_num_minus4 = r.read_ue()?;
+    let pic_order_cnt_type = r.read_ue()?;
+    if pic_order_cnt_type == 0 {
+        let _log2_max_pic_order_cnt_lsb_minus4 = r.read_ue()?;
+    } else if pic_order_cnt_type == 1 {
+        let _delta_pic_order_always_zero_flag = r.read_bit()?;
+        let _offset_for_non_ref_pic = r.read_se()?;
+        let _offset_for_top_to_bottom_field = r.read_se()?;
+        let num_ref_frames_in_pic_order_cnt_cycle = r.read_ue()?;
+        for _ in 0..num_ref_frames_in_pic_order_cnt_cycle {
+            let _offset_for_ref_frame = r.read_se()?;
+        }
+    }
+
+    let _max_num_ref_frames = r.read_ue()?;
+    let _gaps_in_frame_num_value_allowed_flag = r.read_bit()?;
+    let pic_width_in_mbs_minus1 = r.read_ue()?;
+    let pic_height_in_map_units_minus1 = r.read_ue()?;
+    let frame_mbs_only_flag = r.read_bit()?;
+    if frame_mbs_only_flag == 0 {
+        let _mb_adaptive_frame_field_flag = r.read_bit()?;
+    }
+    let _direct_8x8_inference_flag = r.read_bit()?;
+
+    let frame_cropping_flag = r.read_bit()?;
+    let (mut crop_left, mut crop_right, mut crop_top, mut crop_bottom) = (0u32, 0u32, 0u32, 0u32);
+    if frame_cropping_flag != 0 {
+        crop_left = r.read_ue()?;
+        crop_right = r.read_ue()?;
+        crop_top = r.read_ue()?;
+        crop_bottom = r.read_ue()?;
+    }
+
+    let frame_mbs_only = frame_mbs_only_flag != 0;
+    let width = (pic_width_in_mbs_minus1 + 1) * 16;
+    let height = (2 - frame_mbs_only_flag) * (pic_height_in_map_units_minus1 + 1) * 16;
+
+    // 裁剪单位：4:2:0下色度子采样是2，亮度方向按frame_mbs_only再乘2
+    let crop_unit_x = 2u32;
+    let crop_unit_y = if frame_mbs_only { 2 } else { 4 };
+    let width = width.saturating_sub((crop_left + crop_right) * crop_unit_x);
+    let height = height.saturating_sub((crop_top + crop_bottom) * crop_unit_y);
+
+    let mut info = SpsInfo {
+        width,
+        height,
+        fps: None,
+        pic_struct_present: false,
+        cpb_removal_delay_length: None,
+        dpb_output_delay_length: None,
+        time_offset_length: None,
+        matrix_coefficients: None,
+        video_full_range: false,
+    };
+
+    let vui_parameters_present_flag = r.read_bit().unwrap_or(0);
+    if vui_parameters_present_flag != 0 {
+        let aspect_ratio_info_present_flag = r.read_bit()?;
+        if aspect_ratio_info_present_flag != 0 {
+            let aspect_ratio_idc = r.read_bits(8)?;
+            if aspect_ratio_idc == 255 {
+                // Extended_SAR：后面还有32bit的sar_width/sar_height。本项目目前没有
+                // 消费像素宽高比的下游（FrameData只携带方形像素的coded尺寸），这里只需要
+                // 把这32bit消费掉，保持后续VUI字段（matrix_coefficients等）的bit对齐。
+                let _sar_width = r.read_bits(16)?;
+                let _sar_height = r.read_bits(16)?;
+            }
+        }
+
+        let overscan_info_present_flag = r.read_bit()?;
+        if overscan_info_present_flag != 0 {
+            let _overscan_appropriate_flag = r.read_bit()?;
+        }
+
+        let video_signal_type_present_flag = r.read_bit()?;
+        if video_signal_type_present_flag != 0 {
+            let _video_format = r.read_bits(3)?;
+            let video_full_range_flag = r.read_bit()?;
+            info.video_full_range = video_full_range_flag != 0;
+            let colour_description_present_flag = r.read_bit()?;
+            if colour_description_present_flag != 0 {
+                let _colour_primaries = r.read_bits(8)?;
+                let _transfer_characteristics = r.read_bits(8)?;
+                let matrix_coefficients = r.read_bits(8)?;
+                info.matrix_coefficients = Some(matrix_coefficients as u8);
+            }
+        }
+
+        let chroma_loc_info_present_flag = r.read_bit()?;
+        if chroma_loc_info_present_flag != 0 {
+            let _chroma_sample_loc_type_top_field = r.read_ue()?;
+            let _chroma_sample_loc_type_bottom_field = r.read_ue()?;
+        }
+
+        let timing_info_present_flag = r.read_bit()?;
+        if timing_info_present_flag != 0 {
+            let num_units_in_tick = r.read_bits(32)?;
+            let time_scale = r.read_bits(32)?;
+            if num_units_in_tick > 0 {
+                // H.264每帧通常对应2个field period，实际帧率是 time_scale / (2*num_units_in_tick)
+                info.fps = Some(time_scale as f64 / (2.0 * num_units_in_tick as f64));
+            }
+        }
+
+        let nal_hrd_parameters_present_flag = r.read_bit()?;
+        let mut hrd = None;
+        if nal_hrd_parameters_present_flag != 0 {
+            hrd = parse_hrd_parameters(&mut r);
+        }
+        let vcl_hrd_parameters_present_flag = r.read_bit()?;
+        if vcl_hrd_parameters_present_flag != 0 {
+            let vcl_hrd = parse_hrd_parameters(&mut r);
+            hrd = hrd.or(vcl_hrd);
+        }
+        if nal_hrd_parameters_present_flag != 0 || vcl_hrd_parameters_present_flag != 0 {
+            let _low_delay_hrd_flag = r.read_bit()?;
+        }
+        if let Some((cpb_len, dpb_len, time_offset_len)) = hrd {
+            info.cpb_removal_delay_length = Some(cpb_len);
+            info.dpb_output_delay_length = Some(dpb_len);
+            info.time_offset_length = Some(time_offset_len);
+        }
+
+        let pic_struct_present_flag = r.read_bit()?;
+        info.pic_struct_present = pic_struct_present_flag != 0;
+    }
+
+    Some(info)
+}
+
+/// pic_timing SEI里的`clock_timestamp`，给出画面对应的挂钟/媒体时间码。
+#[derive(Debug, Clone, Copy)]
+pub struct SeiTimecode {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub n_frames: u32,
+    pub pic_struct: Option<u32>,
+}
+
+/// SEI user data载荷：CEA-608/708隐藏字幕常见的两种封装方式。
+#[derive(Debug, Clone)]
+pub enum SeiUserData {
+    /// payloadType 4：`user_data_registered_itu_t_t35`
+    ItuT35 { country_code: u8, payload: Vec<u8> },
+    /// payloadType 5：`user_data_unregistered`
+    Unregistered { uuid: [u8; 16], payload: Vec<u8> },
+}
+
+/// 一帧解析出来的SEI附加信息。
+#[derive(Debug, Clone, Default)]
+pub struct SeiMetadata {
+    pub timecode: Option<SeiTimecode>,
+    pub user_data: Vec<SeiUserData>,
+}
+
+/// 读chained bytes编码的SEI `payloadType`/`payloadSize`：先读若干个0xFF（每个记255），
+/// 再读一个非0xFF的终止字节累加进去。
+fn read_sei_chained_value(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut value = 0u32;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value += byte as u32;
+        if byte != 0xFF {
+            break;
+        }
+    }
+    Some(value)
+}
+
+/// 解析一个SEI NAL（已去掉起始码和NAL header）的RBSP，按payloadType分发各个message。
+/// `sps_info`在存在pic_timing时提供HRD字段长度，缺失时跳过pic_timing（宁可漏解析也不要读错位）。
+fn parse_sei_messages(sei_rbsp: &[u8], sps_info: Option<&SpsInfo>) -> SeiMetadata {
+    let rbsp = strip_emulation_prevention(sei_rbsp);
+    let mut meta = SeiMetadata::default();
+    let mut pos = 0usize;
+
+    while pos < rbsp.len() {
+        // rbsp_trailing_bits: 0x80后面全是0，或者已经到末尾
+        if rbsp[pos] == 0x80 && pos == rbsp.len() - 1 {
+            break;
+        }
+
+        let Some(payload_type) = read_sei_chained_value(&rbsp, &mut pos) else { break };
+        let Some(payload_size) = read_sei_chained_value(&rbsp, &mut pos) else { break };
+        let payload_size = payload_size as usize;
+        if pos + payload_size > rbsp.len() {
+            break;
+        }
+        let payload = &rbsp[pos..pos + payload_size];
+
+        match payload_type {
+            1 => {
+                // pic_timing：需要SPS VUI里的HRD字段长度才能正确定位clock_timestamp
+                if let Some(sps) = sps_info {
+                    if let Some(tc) = parse_pic_timing(payload, sps) {
+                        meta.timecode = Some(tc);
+                    }
+                }
+            }
+            4 => {
+                if !payload.is_empty() {
+                    meta.user_data.push(SeiUserData::ItuT35 {
+                        country_code: payload[0],
+                        payload: payload[1..].to_vec(),
+                    });
+                }
+            }
+            5 => {
+                if payload.len() >= 16 {
+                    let mut uuid = [0u8; 16];
+                    uuid.copy_from_slice(&payload[0..16]);
+                    meta.user_data.push(SeiUserData::Unregistered {
+                        uuid,
+                        payload: payload[16..].to_vec(),
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        pos += payload_size;
+    }
+
+    meta
+}
+
+/// 解析pic_timing SEI message，拿到`clock_timestamp`给出的时分秒帧。
+fn parse_pic_timing(payload: &[u8], sps: &SpsInfo) -> Option<SeiTimecode> {
+    let mut r = BitReader::new(payload);
+
+    if let (Some(cpb_len), Some(dpb_len)) = (sps.cpb_removal_delay_length, sps.dpb_output_delay_length) {
+        let _cpb_removal_delay = r.read_bits(cpb_len)?;
+        let _dpb_output_delay = r.read_bits(dpb_len)?;
+    }
+
+    let mut pic_struct = None;
+    if sps.pic_struct_present {
+        let ps = r.read_bits(4)?;
+        pic_struct = Some(ps);
+
+        // 每种pic_struct对应1~3个clock timestamp，我们只取第一个
+        let num_clock_ts = match ps {
+            0 | 1 | 2 => 1,
+            3 | 4 | 7 => 2,
+            5 | 6 | 8 => 3,
+            _ => 0,
+        };
+
+        for i in 0..num_clock_ts {
+            let clock_timestamp_flag = r.read_bit()?;
+            if clock_timestamp_flag == 0 {
+                continue;
+            }
+            let _ct_type = r.read_bits(2)?;
+            let _nuit_field_based_flag = r.read_bit()?;
+            let _counting_type = r.read_bits(5)?;
+            let full_timestamp_flag = r.read_bit()?;
+            let _discontinuity_flag = r.read_bit()?;
+            let _cnt_dropped_flag = r.read_bit()?;
+            let n_frames = r.read_bits(8)?;
+
+            let (mut seconds, mut minutes, mut hours) = (0u32, 0u32, 0u32);
+            if full_timestamp_flag != 0 {
+                seconds = r.read_bits(6)?;
+                minutes = r.read_bits(6)?;
+                hours = r.read_bits(5)?;
+            } else {
+                let seconds_flag = r.read_bit()?;
+                if seconds_flag != 0 {
+                    seconds = r.read_bits(6)?;
+                    let minutes_flag = r.read_bit()?;
+                    if minutes_flag != 0 {
+                        minutes = r.read_bits(6)?;
+                        let hours_flag = r.read_bit()?;
+                        if hours_flag != 0 {
+                            hours = r.read_bits(5)?;
+                        }
+                    }
+                }
+            }
+
+            if let Some(time_offset_len) = sps.time_offset_length {
+                if time_offset_len > 0 {
+                    let _time_offset = r.read_bits(time_offset_len)?;
+                }
+            }
+
+            if i == 0 {
+                return Some(SeiTimecode { hours, minutes, seconds, n_frames, pic_struct });
+            }
+        }
+    }
+
+    None
+}
+
 pub struct Mp4Decoder {
     pool: FrameDataPool,
 }
@@ -226,6 +724,9 @@ impl Mp4Decoder {
         }
     }
 
+    /// 仅用于极少数真正从RGB出发的路径；热路径现在走`extract_yuv_planes`，
+    /// 直接复用openh264解码出的原生YUV420P平面，不再绕一圈RGB。
+    #[allow(dead_code)]
     fn convert_rgb_to_yuv(&mut self, rgb_data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
         let width = width as usize;
         let height = height as usize;
@@ -281,6 +782,44 @@ impl Mp4Decoder {
         
         Ok(yuv_data)
     }
+
+}
+
+/// 直接从openh264的`DecodedYUV`里把Y/U/V平面逐行拷贝进池化的YUV420P缓冲区，
+/// 按各自的stride处理（`linesize != width`时按行拷贝）。
+///
+/// openh264内部本来就是按YUV420P解码的，这样可以省掉`write_rgb8` +
+/// `convert_rgb_to_yuv`这两遍全图像素级的转换。共享给`Mp4Decoder`和`AnnexBDecoder`。
+pub(crate) fn extract_yuv_planes(pool: &mut FrameDataPool, image: &openh264::decoder::DecodedYUV, width: u32, height: u32) -> Vec<u8> {
+    let width = width as usize;
+    let height = height as usize;
+    let uv_width = width / 2;
+    let uv_height = height / 2;
+    let y_size = width * height;
+    let uv_size = uv_width * uv_height;
+    let total_size = y_size + 2 * uv_size;
+
+    let mut yuv_data = pool.get_buffer(total_size);
+    yuv_data.clear();
+
+    let (y_stride, u_stride, v_stride) = image.strides();
+
+    let copy_plane = |plane: &[u8], stride: usize, plane_width: usize, plane_height: usize, out: &mut Vec<u8>| {
+        if stride == plane_width {
+            out.extend_from_slice(&plane[..plane_width * plane_height]);
+        } else {
+            for row in 0..plane_height {
+                let start = row * stride;
+                out.extend_from_slice(&plane[start..start + plane_width]);
+            }
+        }
+    };
+
+    copy_plane(image.y(), y_stride, width, height, &mut yuv_data);
+    copy_plane(image.u(), u_stride, uv_width, uv_height, &mut yuv_data);
+    copy_plane(image.v(), v_stride, uv_width, uv_height, &mut yuv_data);
+
+    yuv_data
 }
 
 impl Decoder for Mp4Decoder {
@@ -302,16 +841,48 @@ impl Decoder for Mp4Decoder {
             .find(|(_, t)| t.media_type().unwrap() == mp4::MediaType::H264)
             .ok_or_else(|| anyhow!("未找到H264视频轨道"))?;
 
-        let width = track.width() as u32;
-        let height = track.height() as u32;
+        let mut width = track.width() as u32;
+        let mut height = track.height() as u32;
         let track_id_owned = *track_id;
         let sample_count = mp4.sample_count(track_id_owned)?;
 
         // 计算视频时长和帧率
         let duration = track.duration().as_secs_f64();
         let video_duration_seconds = duration;
-        let estimated_fps = sample_count as f64 / video_duration_seconds;
-        
+        let mut estimated_fps = sample_count as f64 / video_duration_seconds;
+
+        // 解析SPS VUI，拿到剔除裁剪影响之后的真实宽高（不按SAR换算）和帧率，
+        // 优先于轨道头里的数值（轨道头对有裁剪或VFR的流并不可靠）。
+        let avcc_config = &track
+            .trak
+            .mdia
+            .minf
+            .stbl
+            .stsd
+            .avc1
+            .as_ref()
+            .ok_or_else(|| anyhow!("Track does not contain AVC1 config"))?
+            .avcc;
+        let mut sps_info: Option<SpsInfo> = None;
+        if let Some(first_sps) = avcc_config.sequence_parameter_sets.first() {
+            if let Some(info) = parse_sps(&first_sps.bytes) {
+                if info.width > 0 && info.height > 0 {
+                    width = info.width;
+                    height = info.height;
+                }
+                if let Some(fps) = info.fps {
+                    if fps > 0.0 {
+                        estimated_fps = fps;
+                    }
+                }
+                sps_info = Some(info);
+            }
+        }
+
+        let color_space = sps_info
+            .map(|info| detect_color_space(&info, width, height))
+            .unwrap_or_default();
+
         let final_output_frames = if max_frames == 0 {
             if sample_fps > 0 {
                 (video_duration_seconds * sample_fps as f64) as u32
@@ -333,101 +904,513 @@ impl Decoder for Mp4Decoder {
         println!("MP4视频信息: 时长={:.2}秒, 总帧数={}, 目标输出帧数={}, 帧间隔={:.4}", 
                  video_duration_seconds, sample_count, final_output_frames, frame_interval);
 
-        let mut bitstream_converter = Mp4BitstreamConverter::for_mp4_track(track)?;
+        let bitstream_converter = Mp4BitstreamConverter::for_mp4_track(track)?;
+        let length_size = bitstream_converter.length_size;
+
+        let start_time = std::time::Instant::now();
+
+        // Pass 1（串行）：按采样间隔选出要解码的样本，顺手在每个`IdrSlice`处切出一个新GOP。
+        // 每个GOP配上各自的SPS/PPS之后就是完全独立的比特流，后面可以分给不同的worker线程解码。
+        struct Gop {
+            start_frame_number: u32,
+            samples: Vec<Vec<u8>>,
+        }
+
+        let mut gops: Vec<Gop> = Vec::new();
+        let mut next_sample_index = 1.0;
+        let mut selected_count = 0u32;
+
+        for i in 1..=sample_count {
+            if selected_count >= final_output_frames {
+                break;
+            }
+
+            if frame_interval > 1.0 && (i as f64) < next_sample_index {
+                continue;
+            }
+
+            let Some(sample) = mp4.read_sample(track_id_owned, i)? else {
+                continue;
+            };
+
+            if gops.is_empty() || sample_contains_idr(&sample.bytes, length_size) {
+                gops.push(Gop {
+                    start_frame_number: selected_count + 1,
+                    samples: Vec::new(),
+                });
+            }
+            gops.last_mut().unwrap().samples.push(sample.bytes.to_vec());
+
+            selected_count += 1;
+            if frame_interval > 1.0 {
+                next_sample_index += frame_interval;
+            }
+        }
+
+        let worker_count = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(gops.len().max(1));
+
+        println!(
+            "MP4并行解码: {}个GOP分配给{}个worker线程",
+            gops.len(),
+            worker_count
+        );
+
+        // 按轮询把GOP分给worker，每个worker独占一份解码器/内存池/比特流转换器状态，
+        // 互相之间不需要任何跨线程的参考帧管理（每个GOP补完SPS/PPS后就是自给自足的）。
+        let mut buckets: Vec<Vec<Gop>> = (0..worker_count).map(|_| Vec::new()).collect();
+        for (idx, gop) in gops.into_iter().enumerate() {
+            buckets[idx % worker_count].push(gop);
+        }
+
+        let sps_list = bitstream_converter.sps.clone();
+        let pps_list = bitstream_converter.pps.clone();
+        let estimated_frame_size = (width as usize) * (height as usize) * 3 / 2;
+
+        let worker_results: Vec<Result<Vec<FrameData>>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = buckets
+                .into_iter()
+                .filter(|bucket| !bucket.is_empty())
+                .map(|bucket| {
+                    let sps_list = sps_list.clone();
+                    let pps_list = pps_list.clone();
+                    scope.spawn(move || -> Result<Vec<FrameData>> {
+                        let mut local_pool = FrameDataPool::new(4, estimated_frame_size);
+                        let mut local_frames = Vec::new();
+
+                        for gop in bucket {
+                            let mut converter = Mp4BitstreamConverter {
+                                length_size,
+                                sps: sps_list.clone(),
+                                pps: pps_list.clone(),
+                                new_idr: true,
+                                sps_seen: false,
+                                pps_seen: false,
+                            };
+
+                            let decoder_options = unsafe {
+                                DecoderConfig::new()
+                                    .flush_after_decode(Flush::NoFlush)
+                                    .num_threads(0)
+                            };
+                            let mut decoder = H264Decoder::with_api_config(
+                                openh264::OpenH264API::from_source(),
+                                decoder_options,
+                            )?;
+
+                            let mut buffer = Vec::new();
+                            let mut last_sei: Option<SeiMetadata> = None;
+                            let mut frame_number = gop.start_frame_number;
+
+                            for sample_bytes in &gop.samples {
+                                converter.convert_packet(sample_bytes, &mut buffer);
+
+                                let mut cursor = &buffer[..];
+                                while let Some(pos) = cursor.windows(3).position(|w| w == [0, 0, 1]) {
+                                    let after_start = &cursor[pos + 3..];
+                                    let next = after_start
+                                        .windows(3)
+                                        .position(|w| w == [0, 0, 1])
+                                        .unwrap_or(after_start.len());
+                                    let nal = &after_start[..next];
+                                    if !nal.is_empty() && NalType::from(nal[0] & 0x1F) == NalType::Sei {
+                                        let parsed = parse_sei_messages(&nal[1..], sps_info.as_ref());
+                                        if parsed.timecode.is_some() || !parsed.user_data.is_empty() {
+                                            last_sei = Some(parsed);
+                                        }
+                                    }
+                                    cursor = after_start;
+                                }
+
+                                match decoder.decode(&buffer) {
+                                    Ok(Some(image)) => {
+                                        let yuv_data = extract_yuv_planes(&mut local_pool, &image, width, height);
+                                        local_frames.push(FrameData {
+                                            frame_number,
+                                            width,
+                                            height,
+                                            yuv_data,
+                                            format: ffmpeg_next::util::format::Pixel::YUV420P,
+                                            sei: last_sei.take(),
+                                            color_space,
+                                        });
+                                        frame_number += 1;
+                                    }
+                                    Ok(None) => {}
+                                    Err(err) => println!("GOP并行解码帧错误: {}", err),
+                                }
+                            }
+
+                            for image in decoder.flush_remaining()? {
+                                let yuv_data = extract_yuv_planes(&mut local_pool, &image, width, height);
+                                local_frames.push(FrameData {
+                                    frame_number,
+                                    width,
+                                    height,
+                                    yuv_data,
+                                    format: ffmpeg_next::util::format::Pixel::YUV420P,
+                                    sei: last_sei.take(),
+                                    color_space,
+                                });
+                                frame_number += 1;
+                            }
+                        }
+
+                        Ok(local_frames)
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|_| Err(anyhow!("GOP解码worker线程panic"))))
+                .collect()
+        });
+
+        let mut result_frames = Vec::new();
+        for worker_result in worker_results {
+            result_frames.extend(worker_result?);
+        }
+        result_frames.sort_by_key(|f| f.frame_number);
+        result_frames.truncate(final_output_frames as usize);
+
+        let frame_count = result_frames.len() as u32;
+        let total_duration = start_time.elapsed();
+
+        Ok((ProcessingResult {
+            frames_processed: frame_count,
+            total_duration,
+        }, result_frames))
+    }
+}
+
+/// 在原始字节流中扫描Annex B起始码（3字节 `00 00 01` 或4字节 `00 00 00 01`），
+/// 并把每个NAL单元的payload切片出来。
+///
+/// 跨chunk边界的起始码通过调用方维护的`carry`缓冲区衔接，详见`AnnexBDecoder::feed`。
+struct StartCodeScanner;
+
+impl StartCodeScanner {
+    /// 在`buffer`中查找所有完整的NAL单元，返回各NAL的payload切片（不含起始码）列表，
+    /// 以及buffer中尚未构成完整NAL（即最后一个起始码之后）的剩余部分的起始偏移。
+    fn scan(buffer: &[u8]) -> (Vec<&[u8]>, usize) {
+        let mut starts = Vec::new();
+        let mut i = 0;
+        while i + 2 < buffer.len() {
+            if buffer[i] == 0 && buffer[i + 1] == 0 && buffer[i + 2] == 1 {
+                starts.push(i + 3);
+                i += 3;
+            } else {
+                i += 1;
+            }
+        }
+
+        if starts.is_empty() {
+            return (Vec::new(), 0);
+        }
+
+        let mut nals = Vec::with_capacity(starts.len());
+        for w in starts.windows(2) {
+            let (start, next_start) = (w[0], w[1]);
+            // 回退到下一个起始码之前的0字节前缀，不把它们算进payload里
+            let mut end = next_start - 3;
+            while end > start && buffer[end - 1] == 0 {
+                end -= 1;
+            }
+            nals.push(&buffer[start..end]);
+        }
+
+        let last_start = *starts.last().unwrap();
+        (nals, last_start)
+    }
+}
+
+/// 裸H.264 Annex B / elementary stream解码器。
+///
+/// 与`Mp4Decoder`不同，这里没有MP4容器、没有AVCC头，数据本身已经带有Annex B起始码
+/// （例如摄像头落盘的`.h264`/`.264`文件，或`ffmpeg -f h264`的输出）。
+/// 直接在字节流里扫描起始码切出NAL，积累SPS/PPS/SEI，遇到slice/IDR就把组好的
+/// access unit喂给openh264。
+pub struct AnnexBDecoder {
+    pool: FrameDataPool,
+    read_chunk_size: usize,
+}
+
+impl AnnexBDecoder {
+    pub fn new() -> Self {
+        let estimated_frame_size = (3840 * 2160 * 3 / 2) as usize; // 假设最大4K分辨率
+        Self {
+            pool: FrameDataPool::new(16, estimated_frame_size),
+            read_chunk_size: 1 << 20, // 1MB读取块
+        }
+    }
+
+    /// 仅用于极少数真正从RGB出发的路径；热路径现在走`extract_yuv_planes`，
+    /// 直接复用openh264解码出的原生YUV420P平面，不再绕一圈RGB。
+    #[allow(dead_code)]
+    fn convert_rgb_to_yuv(&mut self, rgb_data: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+        // 与Mp4Decoder::convert_rgb_to_yuv是同一套标量BT.601转换
+        let width = width as usize;
+        let height = height as usize;
+        let y_size = width * height;
+        let uv_size = y_size / 4;
+        let total_size = y_size + 2 * uv_size;
+
+        let mut yuv_data = self.pool.get_buffer(total_size);
+        yuv_data.clear();
+        yuv_data.reserve_exact(total_size);
+
+        for y in 0..height {
+            for x in 0..width {
+                let rgb_idx = (y * width + x) * 3;
+                let r = rgb_data[rgb_idx] as f32;
+                let g = rgb_data[rgb_idx + 1] as f32;
+                let b = rgb_data[rgb_idx + 2] as f32;
+                yuv_data.push((0.299 * r + 0.587 * g + 0.114 * b) as u8);
+            }
+        }
+
+        for y in (0..height).step_by(2) {
+            for x in (0..width).step_by(2) {
+                let rgb_idx = (y * width + x) * 3;
+                let r = rgb_data[rgb_idx] as f32;
+                let g = rgb_data[rgb_idx + 1] as f32;
+                let b = rgb_data[rgb_idx + 2] as f32;
+                yuv_data.push((128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b) as u8);
+            }
+        }
+
+        for y in (0..height).step_by(2) {
+            for x in (0..width).step_by(2) {
+                let rgb_idx = (y * width + x) * 3;
+                let r = rgb_data[rgb_idx] as f32;
+                let g = rgb_data[rgb_idx + 1] as f32;
+                let b = rgb_data[rgb_idx + 2] as f32;
+                yuv_data.push((128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b) as u8);
+            }
+        }
+
+        Ok(yuv_data)
+    }
+}
+
+impl Decoder for AnnexBDecoder {
+    fn extract_frames_streaming(
+        &mut self,
+        input_path: &str,
+        max_frames: u32,
+        _sample_fps: u32,
+    ) -> Result<(ProcessingResult, Vec<FrameData>)> {
+        use std::io::Read;
+
+        let mut file = File::open(input_path)?;
 
         let decoder_options = unsafe {
             DecoderConfig::new()
                 .flush_after_decode(Flush::NoFlush)
-                .num_threads(0) // 使用自动线程数
+                .num_threads(0)
         };
-
         let mut decoder = H264Decoder::with_api_config(
-            openh264::OpenH264API::from_source(), 
-            decoder_options
+            openh264::OpenH264API::from_source(),
+            decoder_options,
         )?;
 
-        let mut buffer = Vec::new();
+        let final_output_frames = if max_frames == 0 { u32::MAX } else { max_frames };
+
+        let mut carry: Vec<u8> = Vec::new();
+        let mut read_buf = vec![0u8; self.read_chunk_size];
+        let mut access_unit: Vec<u8> = Vec::new();
+        let mut have_slice = false;
+
         let mut result_frames = Vec::new();
-        let mut frame_count = 0;
-        let mut next_sample_index = 1.0;
+        let mut frame_count = 0u32;
+        let mut width = 0u32;
+        let mut height = 0u32;
+        let mut sps_info: Option<SpsInfo> = None;
+        let mut last_sei: Option<SeiMetadata> = None;
         let start_time = std::time::Instant::now();
 
-        for i in 1..=sample_count {
+        'read_loop: loop {
             if frame_count >= final_output_frames {
                 break;
             }
 
-            // 如果设置了采样间隔，跳过不需要的帧
-            if frame_interval > 1.0 && (i as f64) < next_sample_index {
-                continue;
+            let n = file.read(&mut read_buf)?;
+            if n == 0 {
+                break;
             }
 
-            let Some(sample) = mp4.read_sample(track_id_owned, i)? else {
-                continue;
-            };
+            // 把上次读取保留下来的未完成NAL（连同它的起始码）接回去，防止NAL或起始码被read边界切断
+            carry.extend_from_slice(&read_buf[..n]);
 
-            bitstream_converter.convert_packet(&sample.bytes, &mut buffer);
-
-            match decoder.decode(&buffer) {
-                Ok(Some(image)) => {
-                    frame_count += 1;
-                    
-                    // 使用write_rgb8方法提取RGB数据
-                    let rgb_len = (width * height * 3) as usize;
-                    let mut rgb_data = vec![0u8; rgb_len];
-                    image.write_rgb8(&mut rgb_data);
-                    
-                    // 将RGB转换为YUV
-                    let yuv_data = self.convert_rgb_to_yuv(&rgb_data, width, height)?;
-
-                    let frame = FrameData {
-                        frame_number: frame_count,
-                        width,
-                        height,
-                        yuv_data,
-                        format: ffmpeg_next::util::format::Pixel::YUV420P, // OpenH264总是输出YUV420P
-                    };
-
-                    result_frames.push(frame);
-                    
-                    if frame_interval > 1.0 {
-                        next_sample_index += frame_interval;
+            let (nals, remainder_start) = StartCodeScanner::scan(&carry);
+            for nal in nals {
+                if nal.is_empty() {
+                    continue;
+                }
+                let nal_type = NalType::from(nal[0] & 0x1F);
+
+                if nal_type == NalType::Sps {
+                    if let Some(info) = parse_sps(nal) {
+                        sps_info = Some(info);
                     }
                 }
-                Ok(None) => {
-                    // 解码器还没准备好提供图像
+                if nal_type == NalType::Sei {
+                    let parsed = parse_sei_messages(&nal[1..], sps_info.as_ref());
+                    if parsed.timecode.is_some() || !parsed.user_data.is_empty() {
+                        last_sei = Some(parsed);
+                    }
                 }
-                Err(err) => {
-                    println!("解码帧错误: {}", err);
+
+                match nal_type {
+                    NalType::Sps | NalType::Pps | NalType::Sei | NalType::Aud => {
+                        if have_slice {
+                            // 上一张图片的access unit已经凑齐，先送去解码
+                            if let Some(image) = decoder.decode(&access_unit)? {
+                                frame_count += 1;
+                                width = image.dimensions().0 as u32;
+                                height = image.dimensions().1 as u32;
+                                let yuv_data = extract_yuv_planes(&mut self.pool, &image, width, height);
+                                result_frames.push(FrameData {
+                                    frame_number: frame_count,
+                                    width,
+                                    height,
+                                    yuv_data,
+                                    format: ffmpeg_next::util::format::Pixel::YUV420P,
+                                    sei: last_sei.take(),
+                                    color_space: sps_info
+                                        .map(|info| detect_color_space(&info, width, height))
+                                        .unwrap_or_default(),
+                                });
+                                if frame_count >= final_output_frames {
+                                    break 'read_loop;
+                                }
+                            }
+                            access_unit.clear();
+                            have_slice = false;
+                        }
+                        access_unit.extend([0, 0, 1]);
+                        access_unit.extend(nal);
+                    }
+                    NalType::Slice | NalType::IdrSlice => {
+                        access_unit.extend([0, 0, 1]);
+                        access_unit.extend(nal);
+                        have_slice = true;
+                    }
+                    _ => {
+                        // 其他NAL（filler、end of sequence等）原样透传进access unit
+                        access_unit.extend([0, 0, 1]);
+                        access_unit.extend(nal);
+                    }
+                }
+            }
+
+            // 保留尚未构成完整NAL的尾部，下一轮读取时接上。连同它前面的3字节起始码一起留下，
+            // 下一轮`scan()`才能从起始码处重新认出这个NAL。
+            let keep_from = remainder_start.saturating_sub(3);
+            carry = carry[keep_from..].to_vec();
+        }
+
+        // EOF时carry里还留着最后一个NAL——它后面没有下一个起始码来终止它，
+        // `scan()`的windows(2)永远不会把它产出来。这里把EOF当成这个NAL的隐式终止符，
+        // 按和循环体里完全一样的规则并入access unit，否则整个流的最后一帧会被静默丢弃。
+        let tail_nal = carry.strip_prefix(&[0u8, 0, 1][..]).unwrap_or(carry.as_slice());
+        if !tail_nal.is_empty() && frame_count < final_output_frames {
+            let nal = tail_nal;
+            let nal_type = NalType::from(nal[0] & 0x1F);
+
+            if nal_type == NalType::Sps {
+                if let Some(info) = parse_sps(nal) {
+                    sps_info = Some(info);
+                }
+            }
+            if nal_type == NalType::Sei {
+                let parsed = parse_sei_messages(&nal[1..], sps_info.as_ref());
+                if parsed.timecode.is_some() || !parsed.user_data.is_empty() {
+                    last_sei = Some(parsed);
+                }
+            }
+
+            match nal_type {
+                NalType::Sps | NalType::Pps | NalType::Sei | NalType::Aud => {
+                    if have_slice {
+                        if let Some(image) = decoder.decode(&access_unit)? {
+                            frame_count += 1;
+                            width = image.dimensions().0 as u32;
+                            height = image.dimensions().1 as u32;
+                            let yuv_data = extract_yuv_planes(&mut self.pool, &image, width, height);
+                            result_frames.push(FrameData {
+                                frame_number: frame_count,
+                                width,
+                                height,
+                                yuv_data,
+                                format: ffmpeg_next::util::format::Pixel::YUV420P,
+                                sei: last_sei.take(),
+                                color_space: sps_info
+                                    .map(|info| detect_color_space(&info, width, height))
+                                    .unwrap_or_default(),
+                            });
+                        }
+                        access_unit.clear();
+                        have_slice = false;
+                    }
+                    access_unit.extend([0, 0, 1]);
+                    access_unit.extend(nal);
+                }
+                NalType::Slice | NalType::IdrSlice => {
+                    access_unit.extend([0, 0, 1]);
+                    access_unit.extend(nal);
+                    have_slice = true;
+                }
+                _ => {
+                    access_unit.extend([0, 0, 1]);
+                    access_unit.extend(nal);
                 }
             }
         }
 
-        // 处理剩余的帧
+        // flush最后一个还没送出的access unit
+        if have_slice && frame_count < final_output_frames && !access_unit.is_empty() {
+            if let Some(image) = decoder.decode(&access_unit)? {
+                frame_count += 1;
+                width = image.dimensions().0 as u32;
+                height = image.dimensions().1 as u32;
+                let yuv_data = extract_yuv_planes(&mut self.pool, &image, width, height);
+                result_frames.push(FrameData {
+                    frame_number: frame_count,
+                    width,
+                    height,
+                    yuv_data,
+                    format: ffmpeg_next::util::format::Pixel::YUV420P,
+                    sei: last_sei.take(),
+                    color_space: sps_info
+                        .map(|info| detect_color_space(&info, width, height))
+                        .unwrap_or_default(),
+                });
+            }
+        }
+
         for image in decoder.flush_remaining()? {
             if frame_count >= final_output_frames {
                 break;
             }
-            
             frame_count += 1;
-            
-            // 使用write_rgb8方法提取RGB数据
-            let rgb_len = (width * height * 3) as usize;
-            let mut rgb_data = vec![0u8; rgb_len];
-            image.write_rgb8(&mut rgb_data);
-            
-            // 将RGB转换为YUV
-            let yuv_data = self.convert_rgb_to_yuv(&rgb_data, width, height)?;
-
-            let frame = FrameData {
+            width = image.dimensions().0 as u32;
+            height = image.dimensions().1 as u32;
+            let yuv_data = extract_yuv_planes(&mut self.pool, &image, width, height);
+            result_frames.push(FrameData {
                 frame_number: frame_count,
                 width,
                 height,
                 yuv_data,
                 format: ffmpeg_next::util::format::Pixel::YUV420P,
-            };
-
-            result_frames.push(frame);
+                sei: last_sei.take(),
+                color_space: sps_info
+                    .map(|info| detect_color_space(&info, width, height))
+                    .unwrap_or_default(),
+            });
         }
 
         let total_duration = start_time.elapsed();
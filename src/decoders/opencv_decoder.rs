@@ -1,15 +1,19 @@
 use anyhow::Result;
 use super::{Decoder, FrameData, FrameDataPool, ProcessingResult};
 
+/// 真正跑OpenCV `VideoCapture`的解码器：用`CAP_PROP_FRAME_COUNT`/`CAP_PROP_FPS`算出跟
+/// FFmpeg路径一样的目标输出帧数/采样间隔，再用`CAP_PROP_POS_MSEC`往目标时间点seek；
+/// 抓到的BGR帧用`cvtColor COLOR_BGR2YUV_I420`转成YUV420P，跟FFmpeg解码器走同一套
+/// `FrameData`/`ChannelFrameData`管线。
 pub struct OpenCVDecoder {
-    _pool: FrameDataPool,
+    pool: FrameDataPool,
 }
 
 impl OpenCVDecoder {
     pub fn new() -> Self {
         let estimated_frame_size = (3840 * 2160 * 3 / 2) as usize; // 假设最大4K分辨率
         Self {
-            _pool: FrameDataPool::new(16, estimated_frame_size),
+            pool: FrameDataPool::new(16, estimated_frame_size),
         }
     }
 }
@@ -21,10 +25,142 @@ impl Decoder for OpenCVDecoder {
         max_frames: u32,
         sample_fps: u32,
     ) -> Result<(ProcessingResult, Vec<FrameData>)> {
-        // 暂时委托给 FFmpeg 解码器，这样可以保持接口但避免 OpenCV 复杂性
-        // 在未来的版本中，可以实现真正的 OpenCV 解码逻辑
-        println!("使用 OpenCV 解码器（当前委托给 FFmpeg 实现）");
-        let mut ffmpeg_decoder = super::ffmpeg_decoder::FFmpegDecoder::new();
-        ffmpeg_decoder.extract_frames_streaming(input_path, max_frames, sample_fps)
+        use opencv::core::{AlgorithmHint, Mat};
+        use opencv::imgproc;
+        use opencv::prelude::*;
+        use opencv::videoio::{self, VideoCapture};
+
+        let mut capture = VideoCapture::from_file(input_path, videoio::CAP_ANY)?;
+        if !capture.is_opened()? {
+            anyhow::bail!("OpenCV VideoCapture无法打开输入: {}", input_path);
+        }
+
+        let reported_frame_count = capture.get(videoio::CAP_PROP_FRAME_COUNT)?;
+        let fps = capture.get(videoio::CAP_PROP_FPS)?;
+
+        // 有些容器（网络流、部分mkv）报出来的frame_count不可信（<=0），这种情况下
+        // 放弃按目标时长/总帧数换算，退化成纯顺序读，靠每帧自己的时间戳判断该不该采样。
+        let trust_frame_count = reported_frame_count > 0.0;
+        let video_duration_seconds = if trust_frame_count && fps > 0.0 {
+            reported_frame_count / fps
+        } else {
+            0.0
+        };
+        let total_video_frames = if trust_frame_count { reported_frame_count as u32 } else { 0 };
+
+        let final_output_frames = if max_frames == 0 {
+            if sample_fps > 0 && video_duration_seconds > 0.0 {
+                (video_duration_seconds * sample_fps as f64) as u32
+            } else if total_video_frames > 0 {
+                total_video_frames
+            } else {
+                u32::MAX // 总帧数也不可信：交给下面的顺序读自然结束（read失败即停）
+            }
+        } else {
+            max_frames
+        };
+
+        let frame_interval = if sample_fps > 0 {
+            1.0 / sample_fps as f64
+        } else if max_frames > 0 && video_duration_seconds > 0.0 {
+            video_duration_seconds / max_frames as f64
+        } else {
+            0.0
+        };
+
+        println!("OpenCV VideoCapture信息: 时长={:.2}秒, 总帧数={}, 目标输出帧数={}, 帧间隔={:.4}秒",
+                 video_duration_seconds, total_video_frames, final_output_frames, frame_interval);
+
+        let mut frames = Vec::new();
+        let mut frame_count = 0u32;
+        let mut next_extract_time = 0.0;
+        let start_time = std::time::Instant::now();
+
+        // 只有在总帧数可信且确实要跳着采样时才尝试按时间seek；一旦发现seek没有真的把
+        // 播放位置挪过去（有些后端/容器的`set`返回true却是no-op），就退化成顺序读+
+        // 时间戳判断，而不是静默漏帧。
+        let mut seek_supported = trust_frame_count && frame_interval > 0.0;
+
+        let mut bgr = Mat::default();
+        loop {
+            if frame_count >= final_output_frames {
+                break;
+            }
+
+            if seek_supported && frame_count > 0 {
+                let target_ms = next_extract_time * 1000.0;
+                if !capture.set(videoio::CAP_PROP_POS_MSEC, target_ms)? {
+                    seek_supported = false;
+                }
+            }
+
+            if !capture.read(&mut bgr)? || bgr.empty() {
+                break; // 流结束
+            }
+
+            let actual_seconds = capture.get(videoio::CAP_PROP_POS_MSEC)? / 1000.0;
+
+            if seek_supported && frame_count > 0
+                && (actual_seconds - next_extract_time).abs() > frame_interval.max(0.001) * 2.0
+            {
+                // seek之后的位置跟目标差太远，说明这个后端其实没真的挪动播放头
+                seek_supported = false;
+            }
+
+            let should_extract = if frame_interval == 0.0 {
+                true
+            } else if seek_supported {
+                true // 已经seek到目标时间点了，抓到的这一帧就是要的
+            } else {
+                actual_seconds >= next_extract_time
+            };
+
+            if !should_extract {
+                continue;
+            }
+
+            if frame_interval > 0.0 {
+                next_extract_time += frame_interval;
+            }
+
+            frame_count += 1;
+
+            let width = bgr.cols() as u32;
+            let height = bgr.rows() as u32;
+
+            let mut yuv_mat = Mat::default();
+            imgproc::cvt_color(&bgr, &mut yuv_mat, imgproc::COLOR_BGR2YUV_I420, 0, AlgorithmHint::ALGO_HINT_DEFAULT)?;
+
+            let y_size = (width * height) as usize;
+            let uv_size = y_size / 4;
+            let total_size = y_size + 2 * uv_size;
+            let yuv_bytes = yuv_mat.data_bytes()?;
+            if yuv_bytes.len() < total_size {
+                anyhow::bail!("OpenCV BGR->YUV420P转换数据不足: 期望{}, 实际{}", total_size, yuv_bytes.len());
+            }
+
+            let mut yuv_data = self.pool.get_buffer(total_size);
+            yuv_data.clear();
+            yuv_data.extend_from_slice(&yuv_bytes[..total_size]);
+
+            frames.push(FrameData {
+                frame_number: frame_count,
+                width,
+                height,
+                yuv_data,
+                format: ffmpeg_next::util::format::Pixel::YUV420P,
+                sei: None,
+                color_space: crate::converters::ColorSpace::default(),
+            });
+        }
+
+        let total_duration = start_time.elapsed();
+        Ok((
+            ProcessingResult {
+                frames_processed: frame_count,
+                total_duration,
+            },
+            frames,
+        ))
     }
-} 
\ No newline at end of file
+}
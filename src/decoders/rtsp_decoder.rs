@@ -0,0 +1,341 @@
+use anyhow::{anyhow, Result};
+use openh264::decoder::{Decoder as H264Decoder, DecoderConfig, Flush};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use super::mp4_decoder::{detect_color_space, extract_yuv_planes, parse_sps, NalType, SpsInfo};
+use super::{Decoder, FrameData, FrameDataPool, ProcessingResult};
+
+/// RFC 3984 RTP/H.264 depacketizer：把RTP payload还原成Annex B的NAL单元。
+///
+/// - Type 1-23：单NAL包，直接前缀`00 00 01`后发出。
+/// - Type 28 (FU-A)：分片包，靠起始/结束bit和RTP序列号把分片拼回一个NAL。
+/// - Type 24 (STAP-A)：聚合包，拆成若干个长度前缀的子NAL。
+///
+/// 一个access unit的边界由RTP marker bit标记。
+pub struct RtpDepacketizer {
+    fu_buffer: Vec<u8>,
+    fu_in_progress: bool,
+    last_seq: Option<u16>,
+    access_unit: Vec<u8>,
+}
+
+impl RtpDepacketizer {
+    pub fn new() -> Self {
+        Self {
+            fu_buffer: Vec::new(),
+            fu_in_progress: false,
+            last_seq: None,
+            access_unit: Vec::new(),
+        }
+    }
+
+    /// 处理一个RTP包，`marker`为RTP头里的marker bit，`seq`是RTP序列号。
+    /// 当marker标记一个access unit结束时，返回组装好的Annex B access unit。
+    pub fn push_packet(&mut self, payload: &[u8], marker: bool, seq: u16) -> Option<Vec<u8>> {
+        if payload.is_empty() {
+            return None;
+        }
+
+        if let Some(last) = self.last_seq {
+            let expected = last.wrapping_add(1);
+            if seq != expected {
+                // 序列号跳变说明中间丢包了：不仅正在重组的FU分片不完整，这个access unit
+                // 里已经攒的其它NAL也可能缺了后续分片引用的前提（比如丢的是SPS/PPS所在的包），
+                // 交给解码器只会产出花屏或直接报错，所以连同已攒的access unit一起丢弃，
+                // 从下一个包开始干净地重新同步。
+                println!("RTP序列号跳变: 期望{}, 实际{}, 丢弃正在重组的分片和未完成的access unit", expected, seq);
+                self.fu_buffer.clear();
+                self.fu_in_progress = false;
+                self.access_unit.clear();
+            }
+        }
+        self.last_seq = Some(seq);
+
+        let nal_type = payload[0] & 0x1F;
+
+        match nal_type {
+            1..=23 => {
+                self.access_unit.extend([0, 0, 1]);
+                self.access_unit.extend_from_slice(payload);
+            }
+            24 => {
+                // STAP-A: 跳过聚合包自己的header字节，逐个解析 2字节长度 + NAL
+                let mut rest = &payload[1..];
+                while rest.len() > 2 {
+                    let nal_size = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+                    rest = &rest[2..];
+                    if rest.len() < nal_size {
+                        break;
+                    }
+                    self.access_unit.extend([0, 0, 1]);
+                    self.access_unit.extend_from_slice(&rest[..nal_size]);
+                    rest = &rest[nal_size..];
+                }
+            }
+            28 => {
+                if payload.len() < 2 {
+                    return None;
+                }
+                let fu_indicator = payload[0];
+                let fu_header = payload[1];
+                let start = fu_header & 0x80 != 0;
+                let end = fu_header & 0x40 != 0;
+
+                if start {
+                    // 用FU indicator的NRI位 OR上FU header的类型位重建原始NAL header
+                    let nal_header = (fu_indicator & 0xE0) | (fu_header & 0x1F);
+                    self.fu_buffer.clear();
+                    self.fu_buffer.push(nal_header);
+                    self.fu_in_progress = true;
+                }
+
+                if self.fu_in_progress {
+                    self.fu_buffer.extend_from_slice(&payload[2..]);
+                }
+
+                if end && self.fu_in_progress {
+                    self.access_unit.extend([0, 0, 1]);
+                    self.access_unit.extend(std::mem::take(&mut self.fu_buffer));
+                    self.fu_in_progress = false;
+                }
+            }
+            _ => {
+                // 未知/保留类型，直接丢弃其payload
+            }
+        }
+
+        if marker && !self.access_unit.is_empty() {
+            Some(std::mem::take(&mut self.access_unit))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RtpDepacketizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 最简化的RTSP(TCP interleaved)取流解码器：建立一条RTSP/TCP连接，
+/// 完成DESCRIBE/SETUP/PLAY握手后，按`$<channel><len>`交织帧格式读取RTP包，
+/// 送入`RtpDepacketizer`还原成Annex B，再交给openh264解码。
+pub struct RtspDecoder {
+    pool: FrameDataPool,
+}
+
+impl RtspDecoder {
+    pub fn new() -> Self {
+        let estimated_frame_size = (3840 * 2160 * 3 / 2) as usize;
+        Self {
+            pool: FrameDataPool::new(16, estimated_frame_size),
+        }
+    }
+
+    /// 解析`rtsp://host:port/path`，返回`(host, port)`。
+    fn parse_url(url: &str) -> Result<(String, u16)> {
+        let rest = url
+            .strip_prefix("rtsp://")
+            .ok_or_else(|| anyhow!("不是有效的rtsp:// URL: {}", url))?;
+        let host_part = rest.split('/').next().unwrap_or(rest);
+        if let Some((host, port)) = host_part.split_once(':') {
+            Ok((host.to_string(), port.parse().unwrap_or(554)))
+        } else {
+            Ok((host_part.to_string(), 554))
+        }
+    }
+
+    /// 发送一条RTSP请求并读取响应头；若响应带`Content-Length`（如DESCRIBE的SDP body），
+    /// 把body也原样读完一并附加到返回值里，避免body残留在socket里被下一次握手
+    /// 误当成响应头解析。`reader`贯穿整次握手复用，防止跨调用丢失BufReader内部
+    /// 已经预读但未消费的字节。
+    fn send_request(
+        stream: &mut TcpStream,
+        reader: &mut BufReader<TcpStream>,
+        request: &str,
+    ) -> Result<String> {
+        stream.write_all(request.as_bytes())?;
+        let mut response = String::new();
+        let mut content_length: usize = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+            if let Some(len) = line
+                .to_lowercase()
+                .strip_prefix("content-length:")
+                .and_then(|v| v.trim().parse::<usize>().ok())
+            {
+                content_length = len;
+            }
+            response.push_str(&line);
+        }
+
+        if content_length > 0 {
+            let mut body = vec![0u8; content_length];
+            reader.read_exact(&mut body)?;
+            response.push_str(&String::from_utf8_lossy(&body));
+        }
+
+        Ok(response)
+    }
+
+    /// 通过RTSP握手打开会话，返回握手阶段一直在用的`BufReader`（而不是底层`TcpStream`），
+    /// 定位到交织RTP的channel 0。握手期间`BufReader`很可能已经预读了紧跟在PLAY响应后面
+    /// 的RTP数据（TCP不保证消息边界，服务端常常是攒够一个TCP段就发，PLAY响应和第一批
+    /// RTP包完全可能挤在同一个段里），如果后续改成从原始`stream`读，这些已经被
+    /// `BufReader`吞进内部缓冲区的字节就会被无声丢弃。必须继续用同一个`BufReader`读。
+    fn open_session(url: &str) -> Result<BufReader<TcpStream>> {
+        let (host, port) = Self::parse_url(url)?;
+        let mut stream = TcpStream::connect((host.as_str(), port))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let describe = format!(
+            "DESCRIBE {} RTSP/1.0\r\nCSeq: 1\r\nAccept: application/sdp\r\n\r\n",
+            url
+        );
+        Self::send_request(&mut stream, &mut reader, &describe)?;
+
+        let setup = format!(
+            "SETUP {}/trackID=0 RTSP/1.0\r\nCSeq: 2\r\nTransport: RTP/AVP/TCP;unicast;interleaved=0-1\r\n\r\n",
+            url
+        );
+        let setup_response = Self::send_request(&mut stream, &mut reader, &setup)?;
+
+        let session_id = setup_response
+            .lines()
+            .find(|l| l.to_lowercase().starts_with("session:"))
+            .and_then(|l| l.split(':').nth(1))
+            .map(|s| s.split(';').next().unwrap_or("").trim().to_string())
+            .unwrap_or_default();
+
+        let play = format!(
+            "PLAY {} RTSP/1.0\r\nCSeq: 3\r\nSession: {}\r\nRange: npt=0.000-\r\n\r\n",
+            url, session_id
+        );
+        Self::send_request(&mut stream, &mut reader, &play)?;
+
+        Ok(reader)
+    }
+}
+
+impl Decoder for RtspDecoder {
+    fn extract_frames_streaming(
+        &mut self,
+        input_path: &str,
+        max_frames: u32,
+        _sample_fps: u32,
+    ) -> Result<(ProcessingResult, Vec<FrameData>)> {
+        let mut reader = Self::open_session(input_path)?;
+
+        let decoder_options = unsafe {
+            DecoderConfig::new()
+                .flush_after_decode(Flush::NoFlush)
+                .num_threads(0)
+        };
+        let mut decoder = H264Decoder::with_api_config(
+            openh264::OpenH264API::from_source(),
+            decoder_options,
+        )?;
+
+        let final_output_frames = if max_frames == 0 { u32::MAX } else { max_frames };
+
+        let mut depacketizer = RtpDepacketizer::new();
+        let mut sps: Option<Vec<u8>> = None;
+        let mut pps: Option<Vec<u8>> = None;
+        let mut sps_info: Option<SpsInfo> = None;
+
+        let mut result_frames = Vec::new();
+        let mut frame_count = 0u32;
+        let mut width;
+        let mut height;
+        let start_time = std::time::Instant::now();
+
+        let mut header = [0u8; 4];
+        while frame_count < final_output_frames {
+            if reader.read_exact(&mut header).is_err() {
+                break; // 流结束或连接断开
+            }
+            if header[0] != b'$' {
+                continue; // 不是交织的RTP数据（可能是RTSP控制消息），跳过重新同步
+            }
+
+            let channel = header[1];
+            let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+            let mut packet = vec![0u8; len];
+            if reader.read_exact(&mut packet).is_err() {
+                break;
+            }
+
+            if channel != 0 || packet.len() < 12 {
+                continue; // channel 1通常是RTCP，这里只关心视频RTP（channel 0）
+            }
+
+            let marker = packet[1] & 0x80 != 0;
+            let seq = u16::from_be_bytes([packet[2], packet[3]]);
+            let rtp_payload = &packet[12..];
+
+            let Some(access_unit) = depacketizer.push_packet(rtp_payload, marker, seq) else {
+                continue;
+            };
+
+            // 扫一遍access unit，从SPS里解析颜色空间等信息，并记下最近一次的SPS/PPS原始字节
+            // （这些字节不会被补发给openh264）
+            let mut cursor = &access_unit[..];
+            while let Some(pos) = cursor.windows(3).position(|w| w == [0, 0, 1]) {
+                let after_start = &cursor[pos + 3..];
+                let next = after_start
+                    .windows(3)
+                    .position(|w| w == [0, 0, 1])
+                    .unwrap_or(after_start.len());
+                let nal = &after_start[..next];
+                if !nal.is_empty() {
+                    match NalType::from(nal[0] & 0x1F) {
+                        NalType::Sps => {
+                            if let Some(info) = parse_sps(nal) {
+                                sps_info = Some(info);
+                            }
+                            sps = Some(nal.to_vec());
+                        }
+                        NalType::Pps => pps = Some(nal.to_vec()),
+                        _ => {}
+                    }
+                }
+                cursor = after_start;
+            }
+
+            match decoder.decode(&access_unit) {
+                Ok(Some(image)) => {
+                    frame_count += 1;
+                    width = image.dimensions().0 as u32;
+                    height = image.dimensions().1 as u32;
+                    let yuv_data = extract_yuv_planes(&mut self.pool, &image, width, height);
+                    result_frames.push(FrameData {
+                        frame_number: frame_count,
+                        width,
+                        height,
+                        yuv_data,
+                        format: ffmpeg_next::util::format::Pixel::YUV420P,
+                        sei: None,
+                        color_space: sps_info
+                            .map(|info| detect_color_space(&info, width, height))
+                            .unwrap_or_default(),
+                    });
+                }
+                Ok(None) => {}
+                Err(err) => println!("RTSP解码帧错误: {}", err),
+            }
+        }
+
+        let _ = (sps, pps); // 记下的SPS/PPS原始字节在这里之后没有别的用处
+
+        let total_duration = start_time.elapsed();
+        Ok((ProcessingResult {
+            frames_processed: frame_count,
+            total_duration,
+        }, result_frames))
+    }
+}
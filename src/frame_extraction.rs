@@ -8,6 +8,171 @@ pub struct ProcessingResult {
     pub total_duration: std::time::Duration,
 }
 
+/// 输出节奏策略。`Interval`是原来固定帧间隔/`sample_fps`的行为；`SceneChange`改成
+/// 按画面变化量决定输出哪一帧，静止画面不会被反复输出，但`frame_interval`（由
+/// `sample_fps`换算）仍然作为相邻两次输出之间的最小间隔，防止画面轻微抖动时
+/// 连续好几帧都判定为“变了”而把结果刷爆。
+#[derive(Debug, Clone, Copy)]
+pub enum Sampling {
+    Interval,
+    SceneChange { threshold: f64 },
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling::Interval
+    }
+}
+
+/// 场景变化判定用的亮度缩略图边长：把Y平面box-average缩到一个很小的固定网格，
+/// 两帧之间比较的是这个网格而不是全分辨率像素，足够分辨"画面变没变"又足够便宜。
+const SCENE_SIGNATURE_GRID: usize = 32;
+const SCENE_SIGNATURE_LEN: usize = SCENE_SIGNATURE_GRID * SCENE_SIGNATURE_GRID;
+
+/// 把一帧的Y平面box-average下采样成`SCENE_SIGNATURE_GRID x SCENE_SIGNATURE_GRID`的
+/// 亮度签名，每个格子取对应区域像素的平均值。
+fn luma_signature(frame: &ffmpeg::util::frame::video::Video) -> [u8; SCENE_SIGNATURE_LEN] {
+    let width = frame.width() as usize;
+    let height = frame.height() as usize;
+    let stride = frame.stride(0) as usize;
+    let y_plane = frame.data(0);
+
+    let mut signature = [0u8; SCENE_SIGNATURE_LEN];
+    for gy in 0..SCENE_SIGNATURE_GRID {
+        let y0 = gy * height / SCENE_SIGNATURE_GRID;
+        let y1 = ((gy + 1) * height / SCENE_SIGNATURE_GRID).max(y0 + 1).min(height);
+        for gx in 0..SCENE_SIGNATURE_GRID {
+            let x0 = gx * width / SCENE_SIGNATURE_GRID;
+            let x1 = ((gx + 1) * width / SCENE_SIGNATURE_GRID).max(x0 + 1).min(width);
+
+            let mut sum = 0u64;
+            let mut count = 0u64;
+            for y in y0..y1 {
+                let row_start = y * stride;
+                for x in x0..x1 {
+                    sum += y_plane[row_start + x] as u64;
+                    count += 1;
+                }
+            }
+            signature[gy * SCENE_SIGNATURE_GRID + gx] = (sum / count.max(1)) as u8;
+        }
+    }
+    signature
+}
+
+/// 两个亮度签名之间的差异分数：逐格绝对差的均值除以255，落在0..1区间。
+fn signature_diff_score(a: &[u8; SCENE_SIGNATURE_LEN], b: &[u8; SCENE_SIGNATURE_LEN]) -> f64 {
+    let total: u64 = a.iter().zip(b.iter())
+        .map(|(x, y)| (*x as i32 - *y as i32).unsigned_abs() as u64)
+        .sum();
+    (total as f64 / SCENE_SIGNATURE_LEN as f64) / 255.0
+}
+
+/// 硬件加速解码类型。`None`（默认）走纯软件解码，跟之前的行为完全一致；其余每个
+/// 变体对应一个平台专属的FFmpeg硬件设备类型，只在对应平台/驱动可用时才能真正用上。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HwAccel {
+    None,
+    Cuda,
+    Vaapi,
+    Videotoolbox,
+    Qsv,
+}
+
+impl Default for HwAccel {
+    fn default() -> Self {
+        HwAccel::None
+    }
+}
+
+impl HwAccel {
+    fn av_device_type(self) -> Option<ffmpeg::ffi::AVHWDeviceType> {
+        use ffmpeg::ffi::AVHWDeviceType::*;
+        match self {
+            HwAccel::None => None,
+            HwAccel::Cuda => Some(AV_HWDEVICE_TYPE_CUDA),
+            HwAccel::Vaapi => Some(AV_HWDEVICE_TYPE_VAAPI),
+            HwAccel::Videotoolbox => Some(AV_HWDEVICE_TYPE_VIDEOTOOLBOX),
+            HwAccel::Qsv => Some(AV_HWDEVICE_TYPE_QSV),
+        }
+    }
+
+    fn av_pixel_format(self) -> ffmpeg::ffi::AVPixelFormat {
+        use ffmpeg::ffi::AVPixelFormat::*;
+        match self {
+            HwAccel::None => AV_PIX_FMT_NONE,
+            HwAccel::Cuda => AV_PIX_FMT_CUDA,
+            HwAccel::Vaapi => AV_PIX_FMT_VAAPI,
+            HwAccel::Videotoolbox => AV_PIX_FMT_VIDEOTOOLBOX,
+            HwAccel::Qsv => AV_PIX_FMT_QSV,
+        }
+    }
+}
+
+/// `get_format`回调要返回的硬件像素格式：回调本身是个`extern "C" fn`，拿不到闭包捕获，
+/// 只能靠线程局部变量在`setup_hwaccel`和回调之间传递“这次想要哪个格式”。提取流程是
+/// 单线程顺序跑的，用thread_local足够，不需要更重的同步原语。
+thread_local! {
+    static HW_PIX_FMT: std::cell::Cell<Option<ffmpeg::ffi::AVPixelFormat>> = std::cell::Cell::new(None);
+}
+
+extern "C" fn negotiate_hw_format(
+    ctx: *mut ffmpeg::ffi::AVCodecContext,
+    formats: *const ffmpeg::ffi::AVPixelFormat,
+) -> ffmpeg::ffi::AVPixelFormat {
+    let wanted = HW_PIX_FMT.with(|cell| cell.get());
+    unsafe {
+        let mut ptr = formats;
+        while *ptr != ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_NONE {
+            if Some(*ptr) == wanted {
+                return *ptr;
+            }
+            ptr = ptr.add(1);
+        }
+        // 驱动没给出对应的硬件格式：交给FFmpeg默认的选择逻辑挑一个软件格式继续解码
+        eprintln!("硬件像素格式协商失败，回退到软件解码");
+        ffmpeg::ffi::avcodec_default_get_format(ctx, formats)
+    }
+}
+
+fn is_hw_pixel_format(format: ffmpeg::util::format::Pixel) -> bool {
+    use ffmpeg::util::format::Pixel;
+    matches!(format, Pixel::CUDA | Pixel::VAAPI | Pixel::VIDEOTOOLBOX | Pixel::QSV)
+}
+
+/// 给解码器挂上硬件设备上下文：创建设备、装`get_format`回调，让解码器在探测到驱动
+/// 真的支持对应硬件格式时优先选它。设备创建失败会在这里直接报错返回，调用方
+/// （`optimize_decoder_for_speed`）负责捕获并回退到软件解码，而不是让整个提取流程
+/// 失败退出；格式协商发生在之后解码时的`negotiate_hw_format`回调里，协商不成时
+/// 由回调打印提示并退回软件像素格式。
+fn setup_hwaccel(decoder_context: &mut ffmpeg::codec::context::Context, hwaccel: HwAccel) -> Result<()> {
+    let device_type = hwaccel
+        .av_device_type()
+        .ok_or_else(|| anyhow::anyhow!("未指定硬件加速类型"))?;
+
+    unsafe {
+        let mut hw_device_ctx: *mut ffmpeg::ffi::AVBufferRef = std::ptr::null_mut();
+        let ret = ffmpeg::ffi::av_hwdevice_ctx_create(
+            &mut hw_device_ctx,
+            device_type,
+            std::ptr::null(),
+            std::ptr::null_mut(),
+            0,
+        );
+        if ret < 0 {
+            anyhow::bail!("创建{:?}硬件设备上下文失败（错误码 {}）", hwaccel, ret);
+        }
+
+        HW_PIX_FMT.with(|cell| cell.set(Some(hwaccel.av_pixel_format())));
+
+        let ctx_ptr = decoder_context.as_mut_ptr();
+        (*ctx_ptr).hw_device_ctx = hw_device_ctx;
+        (*ctx_ptr).get_format = Some(negotiate_hw_format);
+    }
+
+    Ok(())
+}
+
 // 内存池结构，避免频繁分配
 struct FrameDataPool {
     buffers: Vec<Vec<u8>>,
@@ -43,12 +208,22 @@ pub async fn extract_frames_streaming(
     input_path: &str,
     max_frames: u32,
     sample_fps: u32,
+    sampling: Sampling,
+    hwaccel: HwAccel,
+    filter_spec: Option<&str>,
     sender: tokio::sync::mpsc::Sender<crate::converters::ChannelFrameData>,
 ) -> Result<ProcessingResult> {
     use crate::converters::ChannelFrameData;
 
-    let mut input = create_optimized_input_context(input_path)?;
-    
+    let camera_mode = is_camera_source(input_path);
+    let network_mode = is_network_source(input_path);
+    let mut input = if camera_mode {
+        let device = resolve_camera_device(input_path);
+        create_camera_input_context(&device, CAMERA_DEFAULT_WIDTH, CAMERA_DEFAULT_HEIGHT, sample_fps)?
+    } else {
+        create_optimized_input_context(input_path)?
+    };
+
     let video_stream_index = input.streams()
         .enumerate()
         .find(|(_, stream)| stream.parameters().medium() == ffmpeg::media::Type::Video)
@@ -57,53 +232,88 @@ pub async fn extract_frames_streaming(
 
     let stream = input.streams().nth(video_stream_index).unwrap();
     let mut decoder_context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
-    
-    optimize_decoder_for_speed(&mut decoder_context)?;
+
+    optimize_decoder_for_speed(&mut decoder_context, hwaccel)?;
     let mut decoder = decoder_context.decoder().video()?;
 
-    let duration = stream.duration();
-    let frame_rate = stream.avg_frame_rate();
-    
-    let video_duration_seconds = if duration > 0 {
-        duration as f64 * f64::from(stream.time_base())
-    } else {
-        return Err(anyhow::anyhow!("无法获取视频时长信息"));
-    };
-    
-    let total_video_frames = if frame_rate.numerator() > 0 && frame_rate.denominator() > 0 {
-        let fps = frame_rate.numerator() as f64 / frame_rate.denominator() as f64;
-        (video_duration_seconds * fps) as u32
-    } else {
-        return Err(anyhow::anyhow!("无法获取视频帧率信息"));
-    };
-    
-    let final_output_frames = if max_frames == 0 {
-        if sample_fps > 0 {
-            (video_duration_seconds * sample_fps as f64) as u32
+    let mut filter_graph = filter_spec
+        .map(|spec| build_filter_graph(&decoder, stream.time_base(), spec))
+        .transpose()?;
+
+    let (final_output_frames, frame_interval) = if camera_mode {
+        // 摄像头是活体流，没有总时长/总帧数：max_frames=0表示不限，只靠采样间隔节流
+        let final_output_frames = if max_frames == 0 { u32::MAX } else { max_frames };
+        let frame_interval = if sample_fps > 0 { 1.0 / sample_fps as f64 } else { 0.0 };
+        let target_desc = if final_output_frames == u32::MAX {
+            "不限".to_string()
         } else {
-            total_video_frames 
-        }
-    } else {
-        max_frames
-    };
-    
-    let frame_interval = if sample_fps > 0 {
-        1.0 / sample_fps as f64
-    } else if max_frames > 0 {
-        video_duration_seconds / max_frames as f64
+            final_output_frames.to_string()
+        };
+        println!("摄像头采集: 设备={}, 目标分辨率={}x{}, 目标输出帧数={}, 帧间隔={:.4}秒",
+                 resolve_camera_device(input_path), CAMERA_DEFAULT_WIDTH, CAMERA_DEFAULT_HEIGHT,
+                 target_desc, frame_interval);
+        (final_output_frames, frame_interval)
+    } else if network_mode {
+        // 网络直播流同样没有可信的总时长/总帧数（`stream.duration()`常年是0或负数）：
+        // max_frames=0表示不限，只靠采样间隔节流，跟摄像头采集走同一套节流逻辑。
+        let final_output_frames = if max_frames == 0 { u32::MAX } else { max_frames };
+        let frame_interval = if sample_fps > 0 { 1.0 / sample_fps as f64 } else { 0.0 };
+        let target_desc = if final_output_frames == u32::MAX {
+            "不限".to_string()
+        } else {
+            final_output_frames.to_string()
+        };
+        println!("网络直播流: 地址={}, 目标输出帧数={}, 帧间隔={:.4}秒",
+                 input_path, target_desc, frame_interval);
+        (final_output_frames, frame_interval)
     } else {
-        0.0 
+        let duration = stream.duration();
+        let frame_rate = stream.avg_frame_rate();
+
+        let video_duration_seconds = if duration > 0 {
+            duration as f64 * f64::from(stream.time_base())
+        } else {
+            return Err(anyhow::anyhow!("无法获取视频时长信息"));
+        };
+
+        let total_video_frames = if frame_rate.numerator() > 0 && frame_rate.denominator() > 0 {
+            let fps = frame_rate.numerator() as f64 / frame_rate.denominator() as f64;
+            (video_duration_seconds * fps) as u32
+        } else {
+            return Err(anyhow::anyhow!("无法获取视频帧率信息"));
+        };
+
+        let final_output_frames = if max_frames == 0 {
+            if sample_fps > 0 {
+                (video_duration_seconds * sample_fps as f64) as u32
+            } else {
+                total_video_frames
+            }
+        } else {
+            max_frames
+        };
+
+        let frame_interval = if sample_fps > 0 {
+            1.0 / sample_fps as f64
+        } else if max_frames > 0 {
+            video_duration_seconds / max_frames as f64
+        } else {
+            0.0
+        };
+
+        println!("视频信息: 时长={:.2}秒, 总帧数={}, 目标输出帧数={}, 帧间隔={:.4}秒",
+                 video_duration_seconds, total_video_frames, final_output_frames, frame_interval);
+
+        (final_output_frames, frame_interval)
     };
-    
-    println!("视频信息: 时长={:.2}秒, 总帧数={}, 目标输出帧数={}, 帧间隔={:.4}秒", 
-             video_duration_seconds, total_video_frames, final_output_frames, frame_interval);
-    
+
     // 初始化内存池，估算帧大小
     let estimated_frame_size = (3840 * 2160 * 3 / 2) as usize; // 假设最大4K分辨率
     let mut pool = FrameDataPool::new(16, estimated_frame_size); // 增大内存池容量
     
     let mut next_extract_time = 0.0;
     let mut frame_count = 0;
+    let mut scene_reference: Option<[u8; SCENE_SIGNATURE_LEN]> = None;
     let start_time = std::time::Instant::now();
 
     // 保持原始逻辑，只使用内存池优化
@@ -113,40 +323,87 @@ pub async fn extract_frames_streaming(
 
             let mut decoded = ffmpeg::util::frame::video::Video::empty();
             while decoder.receive_frame(&mut decoded).is_ok() && frame_count < final_output_frames {
-                let timestamp = decoded.timestamp().unwrap_or(0) as f64 * f64::from(stream.time_base());
-                
-                let should_extract = if frame_interval == 0.0 {
-                    true
-                } else {
-                    timestamp >= next_extract_time
-                };
-                
-                if !should_extract {
-                    continue;
-                }
-                
-                if frame_interval > 0.0 {
-                    next_extract_time += frame_interval;
+                // 没有filtergraph时直接处理解码出来的这一帧；有的话先喂进buffersrc，
+                // 再把buffersink吐出来的每一帧（可能0帧、1帧或多帧，取决于fps=之类的滤镜）
+                // 当成「这一轮要处理的帧」逐个过一遍同样的采样/提取逻辑。
+                let mut filtered_frames = Vec::new();
+                if let Some(graph) = filter_graph.as_mut() {
+                    graph.get("in").unwrap().source().add(&decoded)?;
+                    loop {
+                        let mut filtered = ffmpeg::util::frame::video::Video::empty();
+                        if graph.get("out").unwrap().sink().frame(&mut filtered).is_err() {
+                            break;
+                        }
+                        filtered_frames.push(filtered);
+                    }
                 }
-                
-                frame_count += 1;
-                
-                let frame_data = extract_yuv_data_optimized(&decoded, &mut pool)?;
-
-                let channel_frame = ChannelFrameData {
-                    frame_number: frame_count,
-                    width: decoded.width(),
-                    height: decoded.height(),
-                    yuv_data: frame_data,
-                    format: decoded.format(),
-                };
-
-                if sender.send(channel_frame).await.is_err() {
-                    break; 
-                }
-                
-                if frame_count >= final_output_frames {
-                    break;
+                let frames_this_round: Box<dyn Iterator<Item = &ffmpeg::util::frame::video::Video>> =
+                    if filter_graph.is_some() {
+                        Box::new(filtered_frames.iter())
+                    } else {
+                        Box::new(std::iter::once(&decoded))
+                    };
+
+                for frame in frames_this_round {
+                    if frame_count >= final_output_frames {
+                        break;
+                    }
+
+                    let timestamp = frame.timestamp().unwrap_or(0) as f64 * f64::from(stream.time_base());
+                    let min_gap_elapsed = frame_interval == 0.0 || timestamp >= next_extract_time;
+
+                    // 待采纳的新场景签名：只有真正判定要输出这一帧时才会落到`scene_reference`里，
+                    // 避免被跳过的帧污染下一次比较的参考画面。
+                    let mut pending_signature = None;
+
+                    let should_extract = match sampling {
+                        Sampling::Interval => min_gap_elapsed,
+                        Sampling::SceneChange { threshold } => {
+                            if !min_gap_elapsed {
+                                false
+                            } else if let Some(reference) = scene_reference.as_ref() {
+                                let signature = luma_signature(frame);
+                                let changed = signature_diff_score(&signature, reference) > threshold;
+                                if changed {
+                                    pending_signature = Some(signature);
+                                }
+                                changed
+                            } else {
+                                // 还没有参考帧，永远输出第一帧并把它定为参考
+                                pending_signature = Some(luma_signature(frame));
+                                true
+                            }
+                        }
+                    };
+
+                    if !should_extract {
+                        continue;
+                    }
+
+                    if let Some(signature) = pending_signature {
+                        scene_reference = Some(signature);
+                    }
+
+                    if frame_interval > 0.0 {
+                        next_extract_time += frame_interval;
+                    }
+
+                    frame_count += 1;
+
+                    let frame_data = extract_yuv_data_optimized(frame, &mut pool)?;
+
+                    let channel_frame = ChannelFrameData {
+                        frame_number: frame_count,
+                        width: frame.width(),
+                        height: frame.height(),
+                        yuv_data: frame_data,
+                        format: frame.format(),
+                        color_space: crate::converters::ColorSpace::default(),
+                    };
+
+                    if sender.send(channel_frame).await.is_err() {
+                        break;
+                    }
                 }
             }
         }
@@ -165,130 +422,268 @@ pub async fn extract_frames_streaming(
 }
 
 fn extract_yuv_data_optimized(decoded: &ffmpeg::util::frame::video::Video, pool: &mut FrameDataPool) -> Result<Vec<u8>> {
-    if decoded.format() == ffmpeg::util::format::Pixel::YUV420P {
-        let width = decoded.width() as usize;
-        let height = decoded.height() as usize;
-        let y_size = width * height;
-        let uv_size = y_size / 4;
-        let total_size = y_size + 2 * uv_size;
-        
-        // 从内存池获取预分配的缓冲区
-        let mut frame_data = pool.get_buffer(total_size);
-        frame_data.reserve_exact(total_size);
-        
-        // 获取各平面数据
-        let y_plane = decoded.data(0);
-        let y_stride = decoded.stride(0) as usize;
-        let u_plane = decoded.data(1);
-        let u_stride = decoded.stride(1) as usize;
-        let v_plane = decoded.data(2);
-        let v_stride = decoded.stride(2) as usize;
-        
-        let uv_width = width / 2;
-        let uv_height = height / 2;
-        
-        // 高效拷贝Y平面
-        if y_stride == width {
-            // 无padding，一次性拷贝
-            unsafe {
-                let src_ptr = y_plane.as_ptr();
-                let old_len = frame_data.len();
-                frame_data.set_len(old_len + y_size);
-                std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), y_size);
-            }
-        } else {
-            // 有padding，批量逐行拷贝
-            for y in 0..height {
-                let src_offset = y * y_stride;
-                unsafe {
-                    let src_ptr = y_plane.as_ptr().add(src_offset);
-                    let old_len = frame_data.len();
-                    frame_data.set_len(old_len + width);
-                    std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), width);
-                }
+    if is_hw_pixel_format(decoded.format()) {
+        // 硬件解码出来的帧数据留在显存/专用内存里，`data(n)`拿不到真正的像素，必须先用
+        // `av_hwframe_transfer_data`下载成一份真正的软件帧（通常是NV12），再走下面这套
+        // 跟软件解码完全一样的平面拷贝逻辑。
+        let mut sw_frame = ffmpeg::util::frame::video::Video::empty();
+        unsafe {
+            let ret = ffmpeg::ffi::av_hwframe_transfer_data(
+                sw_frame.as_mut_ptr(),
+                decoded.as_ptr() as *mut ffmpeg::ffi::AVFrame,
+                0,
+            );
+            if ret < 0 {
+                anyhow::bail!("硬件帧下载到系统内存失败（错误码 {}）", ret);
             }
         }
-        
-        // 高效拷贝U平面
-        if u_stride == uv_width {
+        return extract_yuv_data_optimized(&sw_frame, pool);
+    }
+
+    use ffmpeg::util::format::Pixel;
+
+    let width = decoded.width() as usize;
+    let height = decoded.height() as usize;
+    let y_size = width * height;
+
+    match decoded.format() {
+        Pixel::YUV420P => {
+            let uv_size = y_size / 4;
+            let total_size = y_size + 2 * uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.reserve_exact(total_size);
+
+            let uv_width = width / 2;
+            let uv_height = height / 2;
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, uv_width, uv_height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, uv_width, uv_height);
+
+            Ok(frame_data)
+        }
+        Pixel::NV12 | Pixel::NV21 => {
+            // 半平面4:2:0：Y平面之后紧跟一个高度减半、宽度不变的交织UV（或VU）平面
+            let uv_size = width * (height / 2);
+            let total_size = y_size + uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.reserve_exact(total_size);
+
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, width, height / 2);
+
+            Ok(frame_data)
+        }
+        Pixel::YUV422P => {
+            // 平面4:2:2：色度只在水平方向减半，垂直方向跟Y平面同高
+            let uv_size = (width / 2) * height;
+            let total_size = y_size + 2 * uv_size;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.reserve_exact(total_size);
+
+            let uv_width = width / 2;
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, uv_width, height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, uv_width, height);
+
+            Ok(frame_data)
+        }
+        Pixel::YUV444P => {
+            // 平面4:4:4：三个平面同宽同高，没有任何色度降采样
+            let total_size = y_size * 3;
+            let mut frame_data = pool.get_buffer(total_size);
+            frame_data.reserve_exact(total_size);
+
+            copy_plane(&mut frame_data, decoded.data(0), decoded.stride(0) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(1), decoded.stride(1) as usize, width, height);
+            copy_plane(&mut frame_data, decoded.data(2), decoded.stride(2) as usize, width, height);
+
+            Ok(frame_data)
+        }
+        _ => {
+            // 未识别的格式：退化成只拷贝平面0，保底别崩，但不保证YUV语义正确
+            let data_size = decoded.data(0).len();
+            let mut frame_data = pool.get_buffer(data_size);
             unsafe {
-                let src_ptr = u_plane.as_ptr();
-                let old_len = frame_data.len();
-                frame_data.set_len(old_len + uv_size);
-                std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), uv_size);
-            }
-        } else {
-            for y in 0..uv_height {
-                let src_offset = y * u_stride;
-                unsafe {
-                    let src_ptr = u_plane.as_ptr().add(src_offset);
-                    let old_len = frame_data.len();
-                    frame_data.set_len(old_len + uv_width);
-                    std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), uv_width);
-                }
+                frame_data.set_len(data_size);
+                std::ptr::copy_nonoverlapping(decoded.data(0).as_ptr(), frame_data.as_mut_ptr(), data_size);
             }
+            Ok(frame_data)
         }
-        
-        // 高效拷贝V平面
-        if v_stride == uv_width {
+    }
+}
+
+/// 把解码器里的一个平面按`stride`拷贝进输出buffer的`frame_data`末尾：没有行padding
+/// （`stride == width`）时退化成一次性整块拷贝，否则逐行跳过每行末尾的padding字节。
+fn copy_plane(frame_data: &mut Vec<u8>, plane: &[u8], stride: usize, width: usize, height: usize) {
+    if stride == width {
+        unsafe {
+            let src_ptr = plane.as_ptr();
+            let old_len = frame_data.len();
+            let size = width * height;
+            frame_data.set_len(old_len + size);
+            std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), size);
+        }
+    } else {
+        for row in 0..height {
+            let src_offset = row * stride;
             unsafe {
-                let src_ptr = v_plane.as_ptr();
+                let src_ptr = plane.as_ptr().add(src_offset);
                 let old_len = frame_data.len();
-                frame_data.set_len(old_len + uv_size);
-                std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), uv_size);
+                frame_data.set_len(old_len + width);
+                std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), width);
             }
-        } else {
-            for y in 0..uv_height {
-                let src_offset = y * v_stride;
-                unsafe {
-                    let src_ptr = v_plane.as_ptr().add(src_offset);
-                    let old_len = frame_data.len();
-                    frame_data.set_len(old_len + uv_width);
-                    std::ptr::copy_nonoverlapping(src_ptr, frame_data.as_mut_ptr().add(old_len), uv_width);
-                }
-            }
-        }
-        
-        Ok(frame_data)
-    } else {
-        // 非YUV420P格式使用快速拷贝
-        let data_size = decoded.data(0).len();
-        let mut frame_data = pool.get_buffer(data_size);
-        unsafe {
-            frame_data.set_len(data_size);
-            std::ptr::copy_nonoverlapping(decoded.data(0).as_ptr(), frame_data.as_mut_ptr(), data_size);
         }
-        Ok(frame_data)
     }
 }
 
 
 
-fn optimize_decoder_for_speed(decoder_context: &mut ffmpeg::codec::context::Context) -> Result<()> {
+/// 用`filter_spec`（比如`"scale=320:-1,yadif"`）搭一条`buffer -> ... -> buffersink`的
+/// 滤镜链：`buffer`源按解码器的宽高/像素格式/time_base/SAR配置，跟真实的`buffersrc_add_frame`
+/// 用法一致；末尾总是追加一个`format=yuv420p`，保证不管用户给的滤镜链输出什么格式，
+/// 流出`buffersink`的都还是`extract_yuv_data_optimized`认识的YUV420P。
+fn build_filter_graph(
+    decoder: &ffmpeg::decoder::Video,
+    time_base: ffmpeg::Rational,
+    filter_spec: &str,
+) -> Result<ffmpeg::filter::Graph> {
+    let mut graph = ffmpeg::filter::Graph::new();
+
+    let sar = decoder.aspect_ratio();
+    let args = format!(
+        "video_size={}x{}:pix_fmt={}:time_base={}/{}:pixel_aspect={}/{}",
+        decoder.width(),
+        decoder.height(),
+        decoder.format().descriptor().map(|d| d.name()).unwrap_or("yuv420p"),
+        time_base.numerator(),
+        time_base.denominator(),
+        sar.numerator().max(1),
+        sar.denominator().max(1),
+    );
+
+    let buffer = ffmpeg::filter::find("buffer").ok_or_else(|| anyhow::anyhow!("当前FFmpeg构建没有buffer滤镜"))?;
+    let buffersink = ffmpeg::filter::find("buffersink").ok_or_else(|| anyhow::anyhow!("当前FFmpeg构建没有buffersink滤镜"))?;
+
+    graph.add(&buffer, "in", &args)?;
+    graph.add(&buffersink, "out", "")?;
+
+    let full_spec = format!("{},format=yuv420p", filter_spec);
+
+    graph.output("in", 0)?.input("out", 0)?.parse(&full_spec)?;
+    graph.validate()?;
+
+    Ok(graph)
+}
+
+fn optimize_decoder_for_speed(decoder_context: &mut ffmpeg::codec::context::Context, hwaccel: HwAccel) -> Result<()> {
     // 使用多线程解码
     decoder_context.set_threading(ffmpeg::threading::Config {
         kind: ffmpeg::threading::Type::Frame,
         count: 0, // 自动检测CPU核心数
     });
-    
+
+    // 硬件加速是锦上添花：设备创建或格式协商失败（驱动没装、平台不支持等）只打印一条
+    // 提示就继续往下走，解码器会退回刚设置好的软件多线程路径，而不是让整次提取直接失败。
+    if hwaccel != HwAccel::None {
+        if let Err(e) = setup_hwaccel(decoder_context, hwaccel) {
+            eprintln!("硬件加速（{:?}）初始化失败，回退到软件解码: {}", hwaccel, e);
+        }
+    }
+
     Ok(())
 }
 
+/// 摄像头采集没有CLI配置项，固定用这个分辨率打开设备；真正的输出尺寸仍然由
+/// `--width`/`--height`（缩放目标）控制，两者是独立的概念。
+const CAMERA_DEFAULT_WIDTH: u32 = 1280;
+const CAMERA_DEFAULT_HEIGHT: u32 = 720;
+
+/// `--input`是摄像头设备而不是文件路径：要么直接给`/dev/videoN`节点，要么给个
+/// 纯数字索引（比如`0`），按惯例解析成`/dev/video{索引}`。
+pub fn is_camera_source(input: &str) -> bool {
+    input.starts_with("/dev/video") || input.parse::<u32>().is_ok()
+}
+
+/// `--input`是网络直播流而不是本地文件：`rtsp://`、`rtmp://`、`http(s)://` URL都算。
+/// 这类输入既没有可信的总时长，也需要跟文件完全不同的FFmpeg传输层选项。
+pub fn is_network_source(input: &str) -> bool {
+    input.starts_with("rtsp://")
+        || input.starts_with("rtmp://")
+        || input.starts_with("http://")
+        || input.starts_with("https://")
+}
+
+fn resolve_camera_device(input: &str) -> String {
+    match input.parse::<u32>() {
+        Ok(index) => format!("/dev/video{}", index),
+        Err(_) => input.to_string(),
+    }
+}
+
+/// 列出系统上所有摄像头设备节点。完整的分辨率/像素格式枚举需要v4l2的
+/// `VIDIOC_ENUM_FMT`/`VIDIOC_ENUM_FRAMESIZES` ioctl，FFmpeg本身也不提供跨平台的
+/// 枚举接口，这里先只做最基础的“系统上有哪些/dev/videoN”。
+pub fn list_camera_devices() -> Vec<String> {
+    let mut devices: Vec<String> = std::fs::read_dir("/dev")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.starts_with("video"))
+                .map(|name| format!("/dev/{}", name))
+                .collect()
+        })
+        .unwrap_or_default();
+    devices.sort();
+    devices
+}
+
+/// 用FFmpeg的v4l2解复用器打开摄像头设备，跟文件走同一套解码/提取流水线——
+/// 摄像头产出的帧格式（比如YUYV422）会跟文件里的YUV420P一样被送进
+/// `extract_yuv_data_optimized`，再经过同一个`ChannelFrameData`通道喂给转换器。
+fn create_camera_input_context(device: &str, width: u32, height: u32, fps: u32) -> Result<ffmpeg::format::context::Input> {
+    ffmpeg::device::register_all();
+
+    let input_format = ffmpeg::format::list::input()
+        .find(|f| f.name() == "v4l2")
+        .ok_or_else(|| anyhow::anyhow!("当前FFmpeg构建没有启用v4l2摄像头采集支持"))?;
+
+    let mut opts = ffmpeg::Dictionary::new();
+    opts.set("video_size", &format!("{}x{}", width, height));
+    if fps > 0 {
+        opts.set("framerate", &fps.to_string());
+    }
+
+    ffmpeg::format::input_with_format(&Path::new(device), input_format, opts)
+        .map_err(|e| anyhow::anyhow!("打开摄像头设备{}失败: {}", device, e))
+}
+
 fn create_optimized_input_context(input_path: &str) -> Result<ffmpeg::format::context::Input> {
     use ffmpeg::format;
-    
-    // 创建输入格式选项
+
     let mut format_opts = ffmpeg::Dictionary::new();
-    
-    // 设置更大的缓冲区大小和读取优化
-    format_opts.set("buffer_size", "8388608"); // 8MB buffer (更大)
-    format_opts.set("max_delay", "0"); // 无延迟
-    format_opts.set("fflags", "fastseek+genpts"); // 快速seek + 生成PTS
-    format_opts.set("analyzeduration", "500000"); // 进一步减少分析时间
-    format_opts.set("probesize", "1000000"); // 进一步减少探测大小
-    format_opts.set("max_probe_packets", "50"); // 限制探测包数量
-    
-    // 使用优化的格式选项打开输入
+
+    if is_network_source(input_path) {
+        // 直播流调的是传输层参数：强制TCP避免UDP丢包花屏、设置连接/读超时防止卡死、
+        // 加大环形缓冲区吸收网络抖动、关闭FFmpeg自己的缓冲以降低取流延迟。
+        if input_path.starts_with("rtsp://") {
+            format_opts.set("rtsp_transport", "tcp");
+        }
+        format_opts.set("stimeout", "5000000"); // 5秒连接/读超时（微秒）
+        format_opts.set("rtbufsize", "16777216"); // 16MB环形缓冲区，吸收网络抖动
+        format_opts.set("fflags", "nobuffer"); // 不额外缓冲，尽量低延迟
+        format_opts.set("buffer_size", "1048576"); // 1MB socket缓冲区
+        format_opts.set("max_delay", "500000"); // 500ms最大复用延迟，容忍一定抖动
+    } else {
+        // 本地文件：按文件场景调的分析/探测/seek参数，网络流下没有意义
+        format_opts.set("buffer_size", "8388608"); // 8MB buffer (更大)
+        format_opts.set("max_delay", "0"); // 无延迟
+        format_opts.set("fflags", "fastseek+genpts"); // 快速seek + 生成PTS
+        format_opts.set("analyzeduration", "500000"); // 进一步减少分析时间
+        format_opts.set("probesize", "1000000"); // 进一步减少探测大小
+        format_opts.set("max_probe_packets", "50"); // 限制探测包数量
+    }
+
     let input = format::input_with_dictionary(&Path::new(input_path), format_opts)?;
     Ok(input)
-} 
\ No newline at end of file
+}
\ No newline at end of file
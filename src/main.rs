@@ -4,6 +4,7 @@ use ffmpeg_next as ffmpeg;
 
 mod cli;
 mod converters;
+mod decoders;
 mod wgpu_processor;
 mod frame_extraction;
 mod benchmark;
@@ -34,8 +35,8 @@ async fn main() -> Result<()> {
 
     if cli.benchmark {
         run_benchmark(&cli).await?;
-    } else if let Some(mode) = cli.mode {
-        run_single_mode(mode.into(), &cli).await?;
+    } else if let Some(mode) = cli.converter {
+        run_single_mode(mode, &cli).await?;
     } else {
         show_help_and_demo(&cli).await?;
     }